@@ -0,0 +1,87 @@
+use crate::{run, Actor, ActorError, Envelope, Handler, Message};
+use std::sync::mpsc::{sync_channel, SyncSender, TrySendError};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// A handle to send messages to an actor. Cloning it is cheap.
+///
+/// The actor stops once every `Addr` is gone and its mailbox is empty.
+pub struct Addr<A> {
+    mailbox: SyncSender<Envelope<A>>,
+}
+
+impl<A> Clone for Addr<A> {
+    fn clone(&self) -> Self {
+        Self {
+            mailbox: self.mailbox.clone(),
+        }
+    }
+}
+
+/// Start `actor` on a thread of its own, with room for `capacity` messages
+/// in its mailbox.
+pub fn spawn<A: Actor>(actor: A, capacity: usize) -> Addr<A> {
+    spawn_with_handle(actor, capacity).0
+}
+
+/// Like [`spawn`], with a handle to wait for the actor to stop and get it back.
+pub fn spawn_with_handle<A: Actor>(mut actor: A, capacity: usize) -> (Addr<A>, JoinHandle<A>) {
+    let (mailbox, receiver) = sync_channel(capacity);
+    let thread = std::thread::spawn(move || {
+        run(&mut actor, || receiver.recv().ok());
+        actor
+    });
+    (Addr { mailbox }, thread)
+}
+
+impl<A: Actor> Addr<A> {
+    /// Send `message`, waiting for room in the mailbox, then wait for the reply.
+    pub fn call<M: Message>(&self, message: M) -> Result<M::Reply, ActorError>
+    where
+        A: Handler<M>,
+    {
+        let (envelope, pending) = Envelope::new(message);
+        self.send(envelope)?;
+        pending.recv()
+    }
+
+    /// Like [`call`](Self::call), waiting at most `timeout` for the reply.
+    pub fn call_timeout<M: Message>(
+        &self,
+        message: M,
+        timeout: Duration,
+    ) -> Result<M::Reply, ActorError>
+    where
+        A: Handler<M>,
+    {
+        let (envelope, pending) = Envelope::new(message);
+        self.send(envelope)?;
+        pending.recv_timeout(timeout)
+    }
+
+    /// Like [`call`](Self::call), failing with [`ActorError::Full`] rather
+    /// than waiting for room in the mailbox.
+    pub fn try_call<M: Message>(&self, message: M) -> Result<M::Reply, ActorError>
+    where
+        A: Handler<M>,
+    {
+        let (envelope, pending) = Envelope::new(message);
+        self.mailbox.try_send(envelope).map_err(|e| match e {
+            TrySendError::Full(_) => ActorError::Full,
+            TrySendError::Disconnected(_) => ActorError::Stopped,
+        })?;
+        pending.recv()
+    }
+
+    /// Send `message` without waiting for it to be handled.
+    pub fn cast<M: Message>(&self, message: M) -> Result<(), ActorError>
+    where
+        A: Handler<M>,
+    {
+        self.send(Envelope::cast(message))
+    }
+
+    fn send(&self, envelope: Envelope<A>) -> Result<(), ActorError> {
+        self.mailbox.send(envelope).map_err(|_| ActorError::Stopped)
+    }
+}
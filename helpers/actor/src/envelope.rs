@@ -0,0 +1,241 @@
+use crate::{Actor, ActorError, Context, Handler, Message, ReadHandler};
+use std::fmt;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::sync::mpsc::{sync_channel, Receiver, RecvTimeoutError, SyncSender, TryRecvError};
+use std::time::Duration;
+
+/// A message on its way to an actor of type `A`, along with where to send the reply.
+///
+/// Dropping it unhandled tells the sender that the actor has stopped.
+pub struct Envelope<A> {
+    message: Box<dyn Deliver<A>>,
+    read: bool,
+}
+
+/// Where the reply to a message shows up.
+pub struct Pending<R> {
+    receiver: Receiver<Result<R, ActorError>>,
+}
+
+type Replier<R> = SyncSender<Result<R, ActorError>>;
+
+/// A message of any type, with its reply channel: what an envelope holds.
+trait Deliver<A>: Send {
+    fn deliver(self: Box<Self>, actor: &mut A, ctx: &mut Context);
+
+    fn deliver_read(self: Box<Self>, actor: &A);
+}
+
+struct Write<M: Message> {
+    message: M,
+    replier: Option<Replier<M::Reply>>,
+}
+
+struct Read<M: Message> {
+    message: M,
+    replier: Replier<M::Reply>,
+}
+
+impl<A: Handler<M>, M: Message> Deliver<A> for Write<M> {
+    fn deliver(self: Box<Self>, actor: &mut A, ctx: &mut Context) {
+        let Write { message, replier } = *self;
+        reply(replier, || actor.handle(message, ctx));
+    }
+
+    fn deliver_read(self: Box<Self>, _actor: &A) {
+        unreachable!("Only reads can be handled through a shared reference")
+    }
+}
+
+impl<A: ReadHandler<M>, M: Message> Deliver<A> for Read<M> {
+    fn deliver(self: Box<Self>, actor: &mut A, _ctx: &mut Context) {
+        self.deliver_read(actor);
+    }
+
+    fn deliver_read(self: Box<Self>, actor: &A) {
+        let Read { message, replier } = *self;
+        reply(Some(replier), || actor.handle_read(message));
+    }
+}
+
+/// Send back what `f` returns.
+///
+/// If `f` panics, the sender is told so before the panic carries on.
+fn reply<R>(replier: Option<Replier<R>>, f: impl FnOnce() -> R) {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(reply) => {
+            if let Some(replier) = replier {
+                // The sender may have given up waiting.
+                let _ = replier.send(Ok(reply));
+            }
+        }
+        Err(payload) => {
+            if let Some(replier) = replier {
+                let _ = replier.send(Err(ActorError::Panicked));
+            }
+            resume_unwind(payload);
+        }
+    }
+}
+
+impl<A: Actor> Envelope<A> {
+    /// A message whose reply is waited for.
+    pub fn new<M: Message>(message: M) -> (Self, Pending<M::Reply>)
+    where
+        A: Handler<M>,
+    {
+        let (replier, receiver) = sync_channel(1);
+        let message = Write {
+            message,
+            replier: Some(replier),
+        };
+        (Self::wrap(message, false), Pending { receiver })
+    }
+
+    /// A message nobody waits a reply for.
+    pub fn cast<M: Message>(message: M) -> Self
+    where
+        A: Handler<M>,
+    {
+        let message = Write {
+            message,
+            replier: None,
+        };
+        Self::wrap(message, false)
+    }
+
+    /// A message that only reads from the actor: see [`handle_read`](Self::handle_read).
+    pub fn read<M: Message>(message: M) -> (Self, Pending<M::Reply>)
+    where
+        A: ReadHandler<M>,
+    {
+        let (replier, receiver) = sync_channel(1);
+        let message = Read { message, replier };
+        (Self::wrap(message, true), Pending { receiver })
+    }
+
+    fn wrap(message: impl Deliver<A> + 'static, read: bool) -> Self {
+        Self {
+            message: Box::new(message),
+            read,
+        }
+    }
+
+    /// Whether the envelope was built with [`read`](Self::read).
+    pub fn is_read(&self) -> bool {
+        self.read
+    }
+
+    pub fn handle(self, actor: &mut A, ctx: &mut Context) {
+        self.message.deliver(actor, ctx)
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the envelope wasn't built with [`read`](Self::read).
+    pub fn handle_read(self, actor: &A) {
+        self.message.deliver_read(actor)
+    }
+}
+
+impl<A> fmt::Debug for Envelope<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Envelope")
+            .field("read", &self.read)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<R> Pending<R> {
+    /// Wait for the reply.
+    pub fn recv(&self) -> Result<R, ActorError> {
+        self.receiver.recv().map_err(|_| ActorError::Stopped)?
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<R, ActorError> {
+        self.receiver.recv_timeout(timeout).map_err(|e| match e {
+            RecvTimeoutError::Timeout => ActorError::TimedOut,
+            RecvTimeoutError::Disconnected => ActorError::Stopped,
+        })?
+    }
+
+    /// The reply, if it's in already.
+    pub fn try_recv(&self) -> Option<Result<R, ActorError>> {
+        match self.receiver.try_recv() {
+            Ok(reply) => Some(reply),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(ActorError::Stopped)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Counter(u64);
+
+    impl Actor for Counter {}
+
+    struct Add(u64);
+
+    impl Message for Add {
+        type Reply = u64;
+    }
+
+    impl Handler<Add> for Counter {
+        fn handle(&mut self, Add(n): Add, _ctx: &mut Context) -> u64 {
+            self.0 = self.0.checked_add(n).expect("Overflow");
+            self.0
+        }
+    }
+
+    struct Total;
+
+    impl Message for Total {
+        type Reply = u64;
+    }
+
+    impl ReadHandler<Total> for Counter {
+        fn handle_read(&self, _: Total) -> u64 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn delivers_and_replies() {
+        let mut counter = Counter(0);
+        let mut ctx = Context::default();
+        let (envelope, pending) = Envelope::new(Add(2));
+        assert!(pending.try_recv().is_none());
+        assert!(!envelope.is_read());
+        envelope.handle(&mut counter, &mut ctx);
+        assert_eq!(pending.recv(), Ok(2));
+
+        Envelope::cast(Add(3)).handle(&mut counter, &mut ctx);
+
+        let (envelope, pending) = Envelope::read(Total);
+        assert!(envelope.is_read());
+        envelope.handle_read(&counter);
+        assert_eq!(pending.recv(), Ok(5));
+    }
+
+    #[test]
+    fn reports_panics_and_drops() {
+        let mut counter = Counter(u64::MAX);
+        let (envelope, pending) = Envelope::new(Add(1));
+        let outcome = catch_unwind(AssertUnwindSafe(|| {
+            envelope.handle(&mut counter, &mut Context::default())
+        }));
+        assert!(outcome.is_err());
+        assert_eq!(pending.recv(), Err(ActorError::Panicked));
+
+        let (envelope, pending) = Envelope::<Counter>::new(Add(1));
+        drop(envelope);
+        assert_eq!(pending.try_recv(), Some(Err(ActorError::Stopped)));
+        assert_eq!(
+            pending.recv_timeout(Duration::from_millis(1)),
+            Err(ActorError::Stopped)
+        );
+    }
+}
@@ -0,0 +1,93 @@
+//! A small actor library, distilled from the ticket server.
+//!
+//! An actor owns its state and lives on a thread of its own: the only way to
+//! get at the state is to send the actor a message, through an [`Addr`].
+//! Each message type says what it's answered with ([`Message::Reply`]), and
+//! the actor says how it's handled by implementing [`Handler`] for it.
+//!
+//! [`spawn`] covers the common case: one thread, one bounded mailbox.
+//! Actors with other needs (priorities, supervision, a pool of readers) can
+//! build [`Envelope`]s themselves, queue them however they like and serve them
+//! with [`run`].
+mod addr;
+mod envelope;
+
+pub use addr::{spawn, spawn_with_handle, Addr};
+pub use envelope::{Envelope, Pending};
+
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+
+pub trait Actor: Send + Sized + 'static {
+    /// Called before the first message is handled.
+    fn started(&mut self, _ctx: &mut Context) {}
+
+    /// Called once the actor stops, even if a handler panicked.
+    fn stopped(&mut self) {}
+}
+
+pub trait Message: Send + 'static {
+    type Reply: Send + 'static;
+}
+
+pub trait Handler<M: Message>: Actor {
+    fn handle(&mut self, message: M, ctx: &mut Context) -> M::Reply;
+}
+
+/// A handler that leaves the actor alone: it can run alongside other reads,
+/// see [`Envelope::read`].
+pub trait ReadHandler<M: Message>: Actor {
+    fn handle_read(&self, message: M) -> M::Reply;
+}
+
+/// What a handler can do to the actor it's running in.
+#[derive(Debug, Default)]
+pub struct Context {
+    stopping: bool,
+}
+
+impl Context {
+    /// Stop once the current message is handled. Messages still in the
+    /// mailbox are dropped: their senders get [`ActorError::Stopped`].
+    pub fn stop(&mut self) {
+        self.stopping = true;
+    }
+
+    pub fn is_stopping(&self) -> bool {
+        self.stopping
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum ActorError {
+    #[error("The actor's mailbox is full")]
+    Full,
+    #[error("The actor has stopped")]
+    Stopped,
+    /// The message may still be handled, later on.
+    #[error("The actor didn't reply in time")]
+    TimedOut,
+    #[error("The actor panicked while handling the message")]
+    Panicked,
+}
+
+/// Run `actor` on the current thread, handling the envelopes returned by `next`
+/// until it returns `None` or a handler stops the actor.
+///
+/// A panicking handler stops the actor too: the panic carries on once
+/// [`Actor::stopped`] has been called.
+pub fn run<A: Actor>(actor: &mut A, mut next: impl FnMut() -> Option<Envelope<A>>) {
+    let mut ctx = Context::default();
+    let outcome = catch_unwind(AssertUnwindSafe(|| {
+        actor.started(&mut ctx);
+        while !ctx.is_stopping() {
+            let Some(envelope) = next() else {
+                break;
+            };
+            envelope.handle(actor, &mut ctx);
+        }
+    }));
+    actor.stopped();
+    if let Err(payload) = outcome {
+        resume_unwind(payload);
+    }
+}
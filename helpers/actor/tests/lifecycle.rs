@@ -0,0 +1,116 @@
+use actor::{spawn, spawn_with_handle, Actor, ActorError, Context, Handler, Message};
+use std::sync::mpsc::{channel, Sender};
+use std::time::Duration;
+
+/// Keeps a log of what happened to it, and reports its lifecycle on `events`.
+struct Journal {
+    entries: Vec<String>,
+    events: Sender<&'static str>,
+}
+
+impl Actor for Journal {
+    fn started(&mut self, _ctx: &mut Context) {
+        let _ = self.events.send("started");
+    }
+
+    fn stopped(&mut self) {
+        let _ = self.events.send("stopped");
+    }
+}
+
+struct Append(String);
+
+impl Message for Append {
+    type Reply = usize;
+}
+
+impl Handler<Append> for Journal {
+    fn handle(&mut self, Append(entry): Append, _ctx: &mut Context) -> usize {
+        assert!(!entry.is_empty(), "Empty entry");
+        self.entries.push(entry);
+        self.entries.len()
+    }
+}
+
+struct Sleep(Duration);
+
+impl Message for Sleep {
+    type Reply = ();
+}
+
+impl Handler<Sleep> for Journal {
+    fn handle(&mut self, Sleep(duration): Sleep, _ctx: &mut Context) {
+        std::thread::sleep(duration);
+    }
+}
+
+struct Close;
+
+impl Message for Close {
+    type Reply = ();
+}
+
+impl Handler<Close> for Journal {
+    fn handle(&mut self, _: Close, ctx: &mut Context) {
+        ctx.stop();
+    }
+}
+
+fn journal() -> (Journal, std::sync::mpsc::Receiver<&'static str>) {
+    let (events, receiver) = channel();
+    let journal = Journal {
+        entries: Vec::new(),
+        events,
+    };
+    (journal, receiver)
+}
+
+#[test]
+fn calls_and_casts() {
+    let (journal, events) = journal();
+    let (addr, handle) = spawn_with_handle(journal, 4);
+    assert_eq!(addr.call(Append("a".into())), Ok(1));
+    addr.cast(Append("b".into())).unwrap();
+    assert_eq!(addr.try_call(Append("c".into())), Ok(3));
+
+    drop(addr);
+    let journal = handle.join().unwrap();
+    assert_eq!(journal.entries, ["a", "b", "c"]);
+    assert_eq!(
+        events.try_iter().collect::<Vec<_>>(),
+        ["started", "stopped"]
+    );
+}
+
+#[test]
+fn stops_on_request() {
+    let (journal, events) = journal();
+    let addr = spawn(journal, 4);
+    addr.call(Close).unwrap();
+    assert_eq!(addr.call(Append("a".into())), Err(ActorError::Stopped));
+    assert_eq!(events.iter().collect::<Vec<_>>(), ["started", "stopped"]);
+}
+
+#[test]
+fn stops_on_panic() {
+    let (journal, events) = journal();
+    let (addr, handle) = spawn_with_handle(journal, 4);
+    assert_eq!(addr.call(Append(String::new())), Err(ActorError::Panicked));
+    assert!(handle.join().is_err());
+    assert_eq!(addr.cast(Append("a".into())), Err(ActorError::Stopped));
+    assert_eq!(events.iter().collect::<Vec<_>>(), ["started", "stopped"]);
+}
+
+#[test]
+fn full_mailbox_and_timeouts() {
+    let (journal, _events) = journal();
+    let addr = spawn(journal, 1);
+    addr.cast(Sleep(Duration::from_millis(100))).unwrap();
+    // Queued behind the sleep.
+    assert_eq!(
+        addr.call_timeout(Append("a".into()), Duration::from_millis(10)),
+        Err(ActorError::TimedOut)
+    );
+    assert_eq!(addr.try_call(Append("b".into())), Err(ActorError::Full));
+    assert_eq!(addr.call(Append("c".into())), Ok(2));
+}
@@ -0,0 +1,111 @@
+use crate::filter::TicketFilter;
+use crate::server::Command;
+use ticket_store::data::{Ticket, TicketDraft, TicketId, TicketPatch};
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, oneshot};
+
+/// A handle to talk to the server. Cloning it is cheap.
+#[derive(Clone)]
+pub struct TicketStoreClient {
+    sender: mpsc::Sender<Command>,
+    send_mode: SendMode,
+}
+
+/// What to do when the server's queue is full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SendMode {
+    /// Fail right away with [`ClientError::Overloaded`].
+    #[default]
+    FailFast,
+    /// Wait for the server to make room: backpressure.
+    Block,
+}
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ClientError {
+    #[error("The store is overloaded")]
+    Overloaded,
+    #[error("The store is no longer reachable")]
+    Disconnected,
+}
+
+impl TicketStoreClient {
+    pub(crate) fn new(sender: mpsc::Sender<Command>) -> Self {
+        Self {
+            sender,
+            send_mode: SendMode::default(),
+        }
+    }
+
+    /// A client that handles a full queue according to `send_mode`.
+    pub fn with_send_mode(&self, send_mode: SendMode) -> Self {
+        Self {
+            send_mode,
+            ..self.clone()
+        }
+    }
+
+    pub async fn insert(&self, draft: TicketDraft) -> Result<TicketId, ClientError> {
+        Ok(self.create(draft).await?.id)
+    }
+
+    /// Like [`insert`](Self::insert), returning the whole ticket as it was
+    /// created, in the same round trip.
+    pub async fn create(&self, draft: TicketDraft) -> Result<Ticket, ClientError> {
+        self.call(|response_channel| Command::Insert {
+            draft,
+            response_channel,
+        })
+        .await
+    }
+
+    pub async fn get(&self, id: TicketId) -> Result<Option<Ticket>, ClientError> {
+        self.call(|response_channel| Command::Get {
+            id,
+            response_channel,
+        })
+        .await
+    }
+
+    /// Apply `patch`, returning the updated ticket.
+    /// `None` if there's no ticket with that id.
+    ///
+    /// Unlike the exercise's `update`, which answers with `()` whether the
+    /// ticket exists or not, this matches `ticket_server`'s client.
+    pub async fn update(&self, patch: TicketPatch) -> Result<Option<Ticket>, ClientError> {
+        self.call(|response_channel| Command::Update {
+            patch,
+            response_channel,
+        })
+        .await
+    }
+
+    /// The tickets that match `filter`, in id order.
+    pub async fn list(&self, filter: TicketFilter) -> Result<Vec<Ticket>, ClientError> {
+        self.call(|response_channel| Command::List {
+            filter,
+            response_channel,
+        })
+        .await
+    }
+
+    async fn call<R>(
+        &self,
+        command: impl FnOnce(oneshot::Sender<R>) -> Command,
+    ) -> Result<R, ClientError> {
+        let (response_channel, response) = oneshot::channel();
+        let command = command(response_channel);
+        match self.send_mode {
+            SendMode::FailFast => self.sender.try_send(command).map_err(|e| match e {
+                TrySendError::Full(_) => ClientError::Overloaded,
+                TrySendError::Closed(_) => ClientError::Disconnected,
+            })?,
+            SendMode::Block => self
+                .sender
+                .send(command)
+                .await
+                .map_err(|_| ClientError::Disconnected)?,
+        }
+        response.await.map_err(|_| ClientError::Disconnected)
+    }
+}
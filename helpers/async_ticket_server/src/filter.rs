@@ -0,0 +1,62 @@
+use ticket_store::data::{Status, Ticket};
+
+/// Which tickets to list. The default matches every ticket.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TicketFilter {
+    pub status: Option<Status>,
+    /// Matches titles that contain it, ignoring case.
+    pub title: Option<String>,
+}
+
+impl TicketFilter {
+    pub fn matches(&self, ticket: &Ticket) -> bool {
+        self.status.is_none_or(|status| status == ticket.status)
+            && self.title.as_ref().is_none_or(|title| {
+                ticket
+                    .title
+                    .as_str()
+                    .to_lowercase()
+                    .contains(&title.to_lowercase())
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+    use ticket_fields::test_helpers::ticket_description;
+    use ticket_fields::TicketTitle;
+    use ticket_store::data::TicketId;
+
+    #[test]
+    fn matches() {
+        let ticket = Ticket {
+            id: TicketId::from(1),
+            title: TicketTitle::try_from("Fix the Login page").unwrap(),
+            description: ticket_description(),
+            status: Status::InProgress,
+            created_at: SystemTime::now(),
+            resolved_at: None,
+        };
+        assert!(TicketFilter::default().matches(&ticket));
+
+        let in_progress = TicketFilter {
+            status: Some(Status::InProgress),
+            ..TicketFilter::default()
+        };
+        assert!(in_progress.matches(&ticket));
+
+        let login = TicketFilter {
+            title: Some("login".into()),
+            ..in_progress.clone()
+        };
+        assert!(login.matches(&ticket));
+
+        let done = TicketFilter {
+            status: Some(Status::Done),
+            ..login
+        };
+        assert!(!done.matches(&ticket));
+    }
+}
@@ -0,0 +1,38 @@
+//! The ticket store server from `07_threads/10_patch`, for async code.
+//!
+//! The threaded client blocks on `std::sync::mpsc`: inside a tokio runtime,
+//! that stalls a worker thread and can deadlock the whole runtime (see
+//! `08_futures/06_async_aware_primitives`). Here the server is a tokio task,
+//! commands go through `tokio::sync::mpsc` and replies come back on a
+//! `oneshot` channel, so the client's methods can be awaited.
+//!
+//! The store can also be scripted over TCP, with a text protocol: see [`protocol`].
+mod client;
+mod filter;
+pub mod protocol;
+mod server;
+
+pub use client::{ClientError, SendMode, TicketStoreClient};
+pub use filter::TicketFilter;
+pub use server::Server;
+pub use ticket_store::data;
+
+/// A server with room for `capacity` queued commands, along with a client to
+/// talk to it. The server does nothing until it's [run](Server::run).
+pub fn channel(capacity: usize) -> (TicketStoreClient, Server) {
+    let (sender, receiver) = tokio::sync::mpsc::channel(capacity);
+    (TicketStoreClient::new(sender), Server::new(receiver))
+}
+
+/// Spawn a server on the current runtime, with room for `capacity` queued commands.
+///
+/// It stops once every client is gone.
+///
+/// # Panics
+///
+/// Panics if called outside of a tokio runtime, or if `capacity` is zero.
+pub fn launch(capacity: usize) -> TicketStoreClient {
+    let (client, server) = channel(capacity);
+    tokio::spawn(server.run());
+    client
+}
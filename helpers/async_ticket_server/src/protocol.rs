@@ -0,0 +1,361 @@
+//! The store over TCP, one command per line: handy to script with `nc`.
+//!
+//! ```text
+//! CREATE <title>\t<description>       OK <id>
+//! GET <id>                            OK <ticket>
+//! SET <id> <field>=<value>[\t...]     OK <ticket>
+//! LIST [<field>=<value>[\t...]]       OK <count>, then a <ticket> per line
+//! ```
+//!
+//! A `<ticket>` is `<id>\t<status>\t<title>\t<description>`. Statuses are
+//! `todo`, `in_progress` and `done`. `SET` takes `title`, `description` and
+//! `status`; `LIST` filters on `status`, and on `title` for titles that
+//! contain the value, ignoring case.
+//!
+//! Commands are case-insensitive. Tabs, newlines, carriage returns and
+//! backslashes within titles and descriptions are escaped as `\t`, `\n`, `\r`
+//! and `\\`, both ways.
+//! Anything that goes wrong is reported on a single `ERR <message>` line,
+//! and the connection stays open.
+//!
+//! Connections are served within [`Limits`]: one that's idle for too long,
+//! takes too long to send a line, or sends a line longer than
+//! [`MAX_LINE_LENGTH`] is closed, after a last `ERR <reason>` line.
+use crate::{ClientError, TicketFilter, TicketStoreClient};
+use connections::{CloseReason, Connection, ConnectionManager, Limits};
+use ticket_fields::{TicketDescription, TicketDescriptionError, TicketTitle, TicketTitleError};
+use ticket_store::data::{Status, Ticket, TicketDraft, TicketId, TicketPatch};
+use tokio::net::TcpListener;
+use tokio_util::codec::LinesCodec;
+
+/// The default [`Limits::max_request_size`].
+pub const MAX_LINE_LENGTH: usize = 4096;
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ProtocolError {
+    #[error("Unknown command `{0}`")]
+    UnknownCommand(String),
+    #[error("Usage: {0}")]
+    Usage(&'static str),
+    #[error("Invalid id `{0}`")]
+    InvalidId(String),
+    #[error("Unknown field `{0}`")]
+    UnknownField(String),
+    #[error("Missing value for field `{0}`: expected `{0}=<value>`")]
+    MissingValue(String),
+    #[error("Invalid status `{0}`: expected todo, in_progress or done")]
+    InvalidStatus(String),
+    #[error("Invalid escape sequence `\\{0}`")]
+    InvalidEscape(char),
+    #[error("A backslash must be followed by the character it escapes")]
+    DanglingBackslash,
+    #[error(transparent)]
+    Title(#[from] TicketTitleError),
+    #[error(transparent)]
+    Description(#[from] TicketDescriptionError),
+    #[error("There is no ticket with id {}", u64::from(*.0))]
+    NotFound(TicketId),
+    #[error(transparent)]
+    Store(#[from] ClientError),
+}
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Create(TicketDraft),
+    Get(TicketId),
+    Set(TicketPatch),
+    List(TicketFilter),
+}
+
+/// Serve the store behind `client` on `listener`, within the default [`Limits`],
+/// with lines of up to [`MAX_LINE_LENGTH`] bytes.
+pub async fn serve(listener: TcpListener, client: TicketStoreClient) {
+    let limits = Limits {
+        max_request_size: MAX_LINE_LENGTH,
+        ..Limits::default()
+    };
+    serve_with(listener, client, &ConnectionManager::new(limits)).await
+}
+
+/// Like [`serve`], with the connections managed by `manager`.
+pub async fn serve_with(
+    listener: TcpListener,
+    client: TicketStoreClient,
+    manager: &ConnectionManager,
+) {
+    let max_line_length = manager.limits().max_request_size;
+    manager
+        .serve(listener, |connection| {
+            serve_connection(connection, client.clone(), max_line_length)
+        })
+        .await
+}
+
+async fn serve_connection(
+    mut connection: Connection,
+    client: TicketStoreClient,
+    max_line_length: usize,
+) {
+    let mut lines = LinesCodec::new_with_max_length(max_line_length);
+    let reason = loop {
+        let line = match connection.read_frame(&mut lines).await {
+            Ok(line) => line,
+            Err(reason) => break reason,
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = execute(&client, &line)
+            .await
+            .unwrap_or_else(|e| vec![format!("ERR {e}")]);
+        let reply: String = reply.into_iter().map(|line| line + "\n").collect();
+        if let Err(reason) = connection.write_all(reply.as_bytes()).await {
+            break reason;
+        }
+    };
+    if !matches!(reason, CloseReason::Hangup | CloseReason::Io(_)) {
+        let farewell = format!("ERR {reason}\n");
+        let _ = connection.write_all(farewell.as_bytes()).await;
+    }
+    connection.close(reason).await;
+}
+
+async fn execute(client: &TicketStoreClient, line: &str) -> Result<Vec<String>, ProtocolError> {
+    match parse(line)? {
+        Command::Create(draft) => {
+            let id = client.insert(draft).await?;
+            Ok(vec![format!("OK {}", u64::from(id))])
+        }
+        Command::Get(id) => {
+            let ticket = client.get(id).await?.ok_or(ProtocolError::NotFound(id))?;
+            Ok(vec![format!("OK {}", format_ticket(&ticket))])
+        }
+        Command::Set(patch) => {
+            let id = patch.id;
+            let ticket = client
+                .update(patch)
+                .await?
+                .ok_or(ProtocolError::NotFound(id))?;
+            Ok(vec![format!("OK {}", format_ticket(&ticket))])
+        }
+        Command::List(filter) => {
+            let tickets = client.list(filter).await?;
+            let mut reply = vec![format!("OK {}", tickets.len())];
+            reply.extend(tickets.iter().map(format_ticket));
+            Ok(reply)
+        }
+    }
+}
+
+fn parse(line: &str) -> Result<Command, ProtocolError> {
+    let (name, rest) = line.split_once(' ').unwrap_or((line, ""));
+    match name.to_ascii_uppercase().as_str() {
+        "CREATE" => {
+            let usage = ProtocolError::Usage("CREATE <title>\\t<description>");
+            let (title, description) = rest.split_once('\t').ok_or(usage)?;
+            Ok(Command::Create(TicketDraft {
+                title: TicketTitle::try_from(unescape(title)?)?,
+                description: TicketDescription::try_from(unescape(description)?)?,
+            }))
+        }
+        "GET" => {
+            if rest.is_empty() || rest.contains(' ') {
+                return Err(ProtocolError::Usage("GET <id>"));
+            }
+            Ok(Command::Get(parse_id(rest)?))
+        }
+        "SET" => {
+            let usage = ProtocolError::Usage("SET <id> <field>=<value>[\\t<field>=<value>...]");
+            let (id, fields) = rest.split_once(' ').ok_or(usage)?;
+            let mut patch = TicketPatch {
+                id: parse_id(id)?,
+                title: None,
+                description: None,
+                status: None,
+            };
+            for (field, value) in assignments(fields)? {
+                match field {
+                    "title" => patch.title = Some(TicketTitle::try_from(unescape(value)?)?),
+                    "description" => {
+                        patch.description = Some(TicketDescription::try_from(unescape(value)?)?)
+                    }
+                    "status" => patch.status = Some(parse_status(value)?),
+                    _ => return Err(ProtocolError::UnknownField(field.into())),
+                }
+            }
+            Ok(Command::Set(patch))
+        }
+        "LIST" => {
+            let mut filter = TicketFilter::default();
+            if !rest.is_empty() {
+                for (field, value) in assignments(rest)? {
+                    match field {
+                        "status" => filter.status = Some(parse_status(value)?),
+                        "title" => filter.title = Some(unescape(value)?),
+                        _ => return Err(ProtocolError::UnknownField(field.into())),
+                    }
+                }
+            }
+            Ok(Command::List(filter))
+        }
+        _ => Err(ProtocolError::UnknownCommand(name.into())),
+    }
+}
+
+/// Tab-separated `<field>=<value>` pairs.
+fn assignments(fields: &str) -> Result<Vec<(&str, &str)>, ProtocolError> {
+    fields
+        .split('\t')
+        .map(|assignment| {
+            assignment
+                .split_once('=')
+                .ok_or_else(|| ProtocolError::MissingValue(assignment.into()))
+        })
+        .collect()
+}
+
+fn parse_id(id: &str) -> Result<TicketId, ProtocolError> {
+    id.parse::<u64>()
+        .map(TicketId::from)
+        .map_err(|_| ProtocolError::InvalidId(id.into()))
+}
+
+fn parse_status(status: &str) -> Result<Status, ProtocolError> {
+    match status {
+        "todo" => Ok(Status::ToDo),
+        "in_progress" => Ok(Status::InProgress),
+        "done" => Ok(Status::Done),
+        _ => Err(ProtocolError::InvalidStatus(status.into())),
+    }
+}
+
+fn format_status(status: Status) -> &'static str {
+    match status {
+        Status::ToDo => "todo",
+        Status::InProgress => "in_progress",
+        Status::Done => "done",
+    }
+}
+
+fn format_ticket(ticket: &Ticket) -> String {
+    format!(
+        "{}\t{}\t{}\t{}",
+        u64::from(ticket.id),
+        format_status(ticket.status),
+        escape(ticket.title.as_str()),
+        escape(ticket.description.as_str()),
+    )
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(text: &str) -> Result<String, ProtocolError> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => unescaped.push('\\'),
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some(c) => return Err(ProtocolError::InvalidEscape(c)),
+            None => return Err(ProtocolError::DanglingBackslash),
+        }
+    }
+    Ok(unescaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_commands() {
+        let Command::Create(draft) = parse("CREATE A title\tA description").unwrap() else {
+            panic!("Expected a CREATE");
+        };
+        assert_eq!(draft.title.as_str(), "A title");
+        assert_eq!(draft.description.as_str(), "A description");
+
+        assert_eq!(parse("get 3"), Ok(Command::Get(TicketId::from(3))));
+
+        let Command::Set(patch) = parse("SET 3 status=done\ttitle=New title").unwrap() else {
+            panic!("Expected a SET");
+        };
+        assert_eq!(patch.id, TicketId::from(3));
+        assert_eq!(patch.status, Some(Status::Done));
+        assert_eq!(patch.title.unwrap().as_str(), "New title");
+        assert_eq!(patch.description, None);
+
+        assert_eq!(parse("LIST"), Ok(Command::List(TicketFilter::default())));
+        let filter = TicketFilter {
+            status: Some(Status::ToDo),
+            title: Some("login".into()),
+        };
+        assert_eq!(
+            parse("LIST status=todo\ttitle=login"),
+            Ok(Command::List(filter))
+        );
+    }
+
+    #[test]
+    fn reports_what_is_wrong() {
+        assert_eq!(
+            parse("DELETE 3"),
+            Err(ProtocolError::UnknownCommand("DELETE".into()))
+        );
+        assert!(matches!(parse("GET"), Err(ProtocolError::Usage(_))));
+        assert!(matches!(
+            parse("CREATE A title"),
+            Err(ProtocolError::Usage(_))
+        ));
+        assert_eq!(
+            parse("GET three"),
+            Err(ProtocolError::InvalidId("three".into()))
+        );
+        assert_eq!(
+            parse("SET 3 status=closed"),
+            Err(ProtocolError::InvalidStatus("closed".into()))
+        );
+        assert_eq!(
+            parse("SET 3 owner=me"),
+            Err(ProtocolError::UnknownField("owner".into()))
+        );
+        assert_eq!(
+            parse("SET 3 status"),
+            Err(ProtocolError::MissingValue("status".into()))
+        );
+        assert_eq!(
+            parse("CREATE \tA description"),
+            Err(ProtocolError::Title(TicketTitleError::Empty))
+        );
+    }
+
+    #[test]
+    fn escapes_round_trip() {
+        let text = "Tabs\tnewlines\nreturns\rand back\\slashes";
+        assert_eq!(
+            escape(text),
+            "Tabs\\tnewlines\\nreturns\\rand back\\\\slashes"
+        );
+        assert_eq!(unescape(&escape(text)).unwrap(), text);
+        assert_eq!(unescape("\\x"), Err(ProtocolError::InvalidEscape('x')));
+        assert_eq!(unescape("x\\"), Err(ProtocolError::DanglingBackslash));
+    }
+}
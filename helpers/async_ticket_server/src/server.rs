@@ -0,0 +1,91 @@
+use crate::filter::TicketFilter;
+use ticket_store::data::{Ticket, TicketDraft, TicketId, TicketPatch};
+use ticket_store::TicketStore;
+use tokio::sync::{mpsc, oneshot};
+
+pub(crate) enum Command {
+    Insert {
+        draft: TicketDraft,
+        response_channel: oneshot::Sender<Ticket>,
+    },
+    Get {
+        id: TicketId,
+        response_channel: oneshot::Sender<Option<Ticket>>,
+    },
+    Update {
+        patch: TicketPatch,
+        response_channel: oneshot::Sender<Option<Ticket>>,
+    },
+    List {
+        filter: TicketFilter,
+        response_channel: oneshot::Sender<Vec<Ticket>>,
+    },
+}
+
+/// The task that owns the tickets. See [`channel`](crate::channel).
+pub struct Server {
+    receiver: mpsc::Receiver<Command>,
+    store: TicketStore,
+}
+
+impl Server {
+    pub(crate) fn new(receiver: mpsc::Receiver<Command>) -> Self {
+        Self {
+            receiver,
+            store: TicketStore::new(),
+        }
+    }
+
+    /// Serve commands until every client is gone, then hand the tickets back.
+    pub async fn run(mut self) -> TicketStore {
+        while let Some(command) = self.receiver.recv().await {
+            self.handle(command);
+        }
+        self.store
+    }
+
+    // A client that stopped waiting for its reply is no reason to stop:
+    // send errors are ignored.
+    fn handle(&mut self, command: Command) {
+        match command {
+            Command::Insert {
+                draft,
+                response_channel,
+            } => {
+                let id = self.store.add_ticket(draft);
+                let _ = response_channel.send(self.store[id].clone());
+            }
+            Command::Get {
+                id,
+                response_channel,
+            } => {
+                let _ = response_channel.send(self.store.get(id).cloned());
+            }
+            Command::Update {
+                patch,
+                response_channel,
+            } => {
+                let _ = response_channel.send(update(&mut self.store, patch));
+            }
+            Command::List {
+                filter,
+                response_channel,
+            } => {
+                let tickets = self
+                    .store
+                    .iter()
+                    .filter(|ticket| filter.matches(ticket))
+                    .cloned()
+                    .collect();
+                let _ = response_channel.send(tickets);
+            }
+        }
+    }
+}
+
+/// Apply `patch`, returning the updated ticket.
+fn update(store: &mut TicketStore, patch: TicketPatch) -> Option<Ticket> {
+    let ticket = store.get_mut(patch.id)?;
+    ticket.apply(patch);
+    Some(ticket.clone())
+}
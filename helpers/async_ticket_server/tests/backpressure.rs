@@ -0,0 +1,61 @@
+use async_ticket_server::{channel, ClientError, SendMode};
+use ticket_store::test_helpers::ticket_draft;
+use tokio::task::yield_now;
+
+#[tokio::test]
+async fn fails_fast_when_the_queue_is_full() {
+    let (client, server) = channel(1);
+    // Takes the only slot, and waits for a server that isn't running yet.
+    let queued = tokio::spawn({
+        let client = client.clone();
+        async move { client.insert(ticket_draft()).await }
+    });
+    yield_now().await;
+    assert_eq!(
+        client.insert(ticket_draft()).await,
+        Err(ClientError::Overloaded)
+    );
+
+    let server = tokio::spawn(server.run());
+    let id = queued.await.unwrap().unwrap();
+    assert!(client.get(id).await.unwrap().is_some());
+
+    drop(client);
+    assert_eq!(server.await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn waits_for_room_when_asked_to() {
+    let (client, server) = channel(1);
+    let client = client.with_send_mode(SendMode::Block);
+    let calls: Vec<_> = (0..4)
+        .map(|_| {
+            let client = client.clone();
+            tokio::spawn(async move { client.insert(ticket_draft()).await })
+        })
+        .collect();
+    yield_now().await;
+    tokio::spawn(server.run());
+    let mut ids = Vec::new();
+    for call in calls {
+        ids.push(call.await.unwrap().unwrap());
+    }
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), 4);
+}
+
+#[tokio::test]
+async fn reports_a_stopped_server() {
+    let (client, server) = channel(1);
+    drop(server);
+    assert_eq!(
+        client.insert(ticket_draft()).await,
+        Err(ClientError::Disconnected)
+    );
+    let client = client.with_send_mode(SendMode::Block);
+    assert_eq!(
+        client.insert(ticket_draft()).await,
+        Err(ClientError::Disconnected)
+    );
+}
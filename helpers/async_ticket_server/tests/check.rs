@@ -0,0 +1,70 @@
+use async_ticket_server::data::{Status, TicketId, TicketPatch};
+use async_ticket_server::{launch, TicketFilter};
+use ticket_store::test_helpers::ticket_draft;
+
+// A single-threaded runtime: a blocking client would deadlock here.
+#[tokio::test]
+async fn works() {
+    let client = launch(5);
+    let draft = ticket_draft();
+    let ticket_id = client.insert(draft.clone()).await.unwrap();
+
+    let ticket = client.get(ticket_id).await.unwrap().unwrap();
+    assert_eq!(ticket_id, ticket.id);
+    assert_eq!(ticket.status, Status::ToDo);
+    assert_eq!(ticket.title, draft.title);
+    assert_eq!(ticket.description, draft.description);
+
+    let patch = TicketPatch {
+        id: ticket_id,
+        title: None,
+        description: None,
+        status: Some(Status::InProgress),
+    };
+    let updated = client.update(patch).await.unwrap().unwrap();
+    assert_eq!(updated.status, Status::InProgress);
+
+    let ticket = client.get(ticket_id).await.unwrap().unwrap();
+    assert_eq!(ticket, updated);
+}
+
+#[tokio::test]
+async fn missing_tickets() {
+    let client = launch(5);
+    let id = TicketId::from(42);
+    assert_eq!(client.get(id).await, Ok(None));
+    let patch = TicketPatch {
+        id,
+        title: None,
+        description: None,
+        status: Some(Status::Done),
+    };
+    assert_eq!(client.update(patch).await, Ok(None));
+}
+
+#[tokio::test]
+async fn lists_with_a_filter() {
+    let client = launch(5);
+    let mut ids = Vec::new();
+    for _ in 0..3 {
+        let draft = ticket_draft();
+        ids.push(client.insert(draft).await.unwrap());
+    }
+    let patch = TicketPatch {
+        id: ids[1],
+        title: None,
+        description: None,
+        status: Some(Status::Done),
+    };
+    client.update(patch).await.unwrap().unwrap();
+
+    let all = client.list(TicketFilter::default()).await.unwrap();
+    assert_eq!(all.len(), 3);
+    let done = TicketFilter {
+        status: Some(Status::Done),
+        ..TicketFilter::default()
+    };
+    let done = client.list(done).await.unwrap();
+    assert_eq!(done.len(), 1);
+    assert_eq!(done[0].id, ids[1]);
+}
@@ -0,0 +1,147 @@
+use async_ticket_server::launch;
+use async_ticket_server::protocol::{serve, serve_with, MAX_LINE_LENGTH};
+use connections::{ConnectionManager, Limits};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+
+struct Connection {
+    reader: Lines<BufReader<OwnedReadHalf>>,
+    writer: OwnedWriteHalf,
+}
+
+impl Connection {
+    async fn open() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, launch(16)));
+        Self::connect(addr).await
+    }
+
+    async fn connect(addr: SocketAddr) -> Self {
+        let (reader, writer) = TcpStream::connect(addr).await.unwrap().into_split();
+        Self {
+            reader: BufReader::new(reader).lines(),
+            writer,
+        }
+    }
+
+    /// Send `command`, returning the first line of the reply.
+    async fn send(&mut self, command: &str) -> String {
+        self.writer
+            .write_all(format!("{command}\n").as_bytes())
+            .await
+            .unwrap();
+        self.line().await
+    }
+
+    async fn line(&mut self) -> String {
+        self.reader.next_line().await.unwrap().unwrap()
+    }
+}
+
+#[tokio::test]
+async fn create_get_set_and_list() {
+    let mut connection = Connection::open().await;
+    assert_eq!(
+        connection.send("CREATE Fix the login\tIt's broken").await,
+        "OK 0"
+    );
+    assert_eq!(
+        connection.send("CREATE Write docs\tFor the\\tAPI").await,
+        "OK 1"
+    );
+    assert_eq!(
+        connection.send("GET 0").await,
+        "OK 0\ttodo\tFix the login\tIt's broken"
+    );
+    assert_eq!(
+        connection.send("GET 1").await,
+        "OK 1\ttodo\tWrite docs\tFor the\\tAPI"
+    );
+    assert_eq!(
+        connection
+            .send("SET 0 status=done\ttitle=Fixed the login")
+            .await,
+        "OK 0\tdone\tFixed the login\tIt's broken"
+    );
+
+    assert_eq!(connection.send("LIST").await, "OK 2");
+    assert!(connection.line().await.starts_with("0\tdone\t"));
+    assert!(connection.line().await.starts_with("1\ttodo\t"));
+    assert_eq!(connection.send("LIST status=todo").await, "OK 1");
+    assert!(connection.line().await.starts_with("1\ttodo\t"));
+    assert_eq!(
+        connection.send("list title=LOGIN\tstatus=todo").await,
+        "OK 0"
+    );
+}
+
+#[tokio::test]
+async fn errors_keep_the_connection_open() {
+    let mut connection = Connection::open().await;
+    assert_eq!(
+        connection.send("DELETE 0").await,
+        "ERR Unknown command `DELETE`"
+    );
+    assert_eq!(
+        connection.send("GET 0").await,
+        "ERR There is no ticket with id 0"
+    );
+    assert_eq!(
+        connection.send("CREATE \tA description").await,
+        "ERR The title cannot be empty"
+    );
+    assert_eq!(
+        connection.send("SET 0 status=closed").await,
+        "ERR Invalid status `closed`: expected todo, in_progress or done"
+    );
+    // Blank lines are ignored.
+    assert_eq!(
+        connection.send("\nCREATE A title\tA description").await,
+        "OK 0"
+    );
+}
+
+#[tokio::test]
+async fn overly_long_lines_close_the_connection() {
+    let mut connection = Connection::open().await;
+    let long = "x".repeat(MAX_LINE_LENGTH + 1);
+    assert_eq!(
+        connection.send(&format!("CREATE {long}\t{long}")).await,
+        format!("ERR The request is larger than {MAX_LINE_LENGTH} bytes")
+    );
+    assert_eq!(connection.reader.next_line().await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn overly_long_lines_are_caught_when_they_arrive_in_one_go() {
+    let mut connection = Connection::open().await;
+    let long = "x".repeat(MAX_LINE_LENGTH / 2);
+    assert_eq!(
+        connection.send(&format!("CREATE {long}\t{long}")).await,
+        format!("ERR The request is larger than {MAX_LINE_LENGTH} bytes")
+    );
+    assert_eq!(connection.reader.next_line().await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn idle_connections_are_closed() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let manager = ConnectionManager::new(Limits {
+        idle_timeout: Duration::from_millis(100),
+        linger: Duration::from_millis(10),
+        ..Limits::default()
+    });
+    tokio::spawn(async move { serve_with(listener, launch(16), &manager).await });
+    let mut connection = Connection::connect(addr).await;
+    assert_eq!(
+        connection.send("CREATE A title\tA description").await,
+        "OK 0"
+    );
+    assert_eq!(connection.line().await, "ERR Idle for more than 100ms");
+    assert_eq!(connection.reader.next_line().await.unwrap(), None);
+}
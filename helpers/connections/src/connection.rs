@@ -0,0 +1,276 @@
+use crate::{log_close, Limits};
+use bytes::{Buf, BytesMut};
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::{timeout_at, Instant};
+use tokio_util::codec::{Decoder, Encoder};
+
+/// Why a connection ended, or is about to.
+#[derive(Debug, thiserror::Error)]
+pub enum CloseReason {
+    #[error("The client hung up")]
+    Hangup,
+    #[error("Idle for more than {0:?}")]
+    IdleTimeout(Duration),
+    #[error("The request took more than {0:?} to arrive")]
+    ReadTimeout(Duration),
+    #[error("The request is larger than {0} bytes")]
+    RequestTooLarge(usize),
+    #[error("Malformed request: {0}")]
+    Malformed(String),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// An accepted connection, read from within the manager's [`Limits`].
+///
+/// Reads buffer what they get: a read that fails with a timeout loses nothing,
+/// it's all still there for the next one.
+pub struct Connection {
+    stream: TcpStream,
+    peer: SocketAddr,
+    limits: Limits,
+    buffer: BytesMut,
+}
+
+impl Connection {
+    pub(crate) fn new(stream: TcpStream, peer: SocketAddr, limits: Limits) -> Self {
+        Self {
+            stream,
+            peer,
+            limits,
+            buffer: BytesMut::new(),
+        }
+    }
+
+    pub fn peer(&self) -> SocketAddr {
+        self.peer
+    }
+
+    /// The next frame, as cut out by `decoder`.
+    ///
+    /// A frame must end within [`Limits::max_request_size`] bytes: past that,
+    /// it fails with [`CloseReason::RequestTooLarge`], even if the decoder gave
+    /// up on it first. If the client hangs up halfway through a frame, what's
+    /// left is handed to [`Decoder::decode_eof`].
+    pub async fn read_frame<D>(&mut self, decoder: &mut D) -> Result<D::Item, CloseReason>
+    where
+        D: Decoder,
+        D::Error: fmt::Display,
+    {
+        let malformed = |e: D::Error| CloseReason::Malformed(e.to_string());
+        let mut deadline = self.deadline();
+        loop {
+            let decoded = decoder.decode(&mut self.buffer);
+            if let Ok(Some(frame)) = decoded {
+                return Ok(frame);
+            }
+            self.check_size()?;
+            decoded.map_err(malformed)?;
+            if !self.fill(&mut deadline).await? {
+                return match decoder.decode_eof(&mut self.buffer).map_err(malformed)? {
+                    Some(frame) => Ok(frame),
+                    None => Err(CloseReason::Hangup),
+                };
+            }
+        }
+    }
+
+    /// Everything the client sends until it shuts its end down, as a single request.
+    pub async fn read_to_end(&mut self) -> Result<BytesMut, CloseReason> {
+        let mut deadline = self.deadline();
+        while self.fill(&mut deadline).await? {}
+        Ok(self.buffer.split())
+    }
+
+    pub async fn write_frame<E, T>(&mut self, encoder: &mut E, item: T) -> Result<(), CloseReason>
+    where
+        E: Encoder<T>,
+        E::Error: Into<io::Error>,
+    {
+        let mut bytes = BytesMut::new();
+        encoder.encode(item, &mut bytes).map_err(Into::into)?;
+        self.write_all(&bytes).await
+    }
+
+    pub async fn write_all(&mut self, bytes: &[u8]) -> Result<(), CloseReason> {
+        Ok(self.stream.write_all(bytes).await?)
+    }
+
+    /// Shut the connection down, logging `reason`.
+    ///
+    /// Whatever the client still sends is read and dropped for up to
+    /// [`Limits::linger`], so that it sees everything that was written to it
+    /// before the connection goes away.
+    pub async fn close(mut self, reason: CloseReason) {
+        log_close(self.peer, &reason);
+        if self.stream.shutdown().await.is_err() {
+            return;
+        }
+        let mut sink = [0; 1024];
+        let _ = tokio::time::timeout(self.limits.linger, async {
+            while let Ok(1..) = self.stream.read(&mut sink).await {}
+        })
+        .await;
+    }
+
+    /// When the next request must have arrived by: if some of it is buffered
+    /// already, it has started.
+    fn deadline(&self) -> Deadline {
+        if self.buffer.has_remaining() {
+            Deadline::reading(&self.limits)
+        } else {
+            Deadline::idle(&self.limits)
+        }
+    }
+
+    fn check_size(&self) -> Result<(), CloseReason> {
+        if self.buffer.len() > self.limits.max_request_size {
+            return Err(CloseReason::RequestTooLarge(self.limits.max_request_size));
+        }
+        Ok(())
+    }
+
+    /// Read some more into the buffer, within `deadline` and the size limit.
+    /// Returns `false` once the client shut its end down.
+    ///
+    /// The buffer never grows past one byte over the limit, however much the
+    /// client sends at once: that byte is how a request too large is told apart.
+    async fn fill(&mut self, deadline: &mut Deadline) -> Result<bool, CloseReason> {
+        self.check_size()?;
+        let room = self.limits.max_request_size + 1 - self.buffer.len();
+        let mut stream = (&mut self.stream).take(room as u64);
+        let read = stream.read_buf(&mut self.buffer);
+        let n = timeout_at(deadline.at, read)
+            .await
+            .map_err(|_| deadline.expired())??;
+        if n > 0 && deadline.idle {
+            *deadline = Deadline::reading(&self.limits);
+        }
+        Ok(n > 0)
+    }
+}
+
+struct Deadline {
+    at: Instant,
+    /// Whether it's waiting for a request to start.
+    idle: bool,
+    after: Duration,
+}
+
+impl Deadline {
+    fn idle(limits: &Limits) -> Self {
+        Self {
+            at: Instant::now() + limits.idle_timeout,
+            idle: true,
+            after: limits.idle_timeout,
+        }
+    }
+
+    fn reading(limits: &Limits) -> Self {
+        Self {
+            at: Instant::now() + limits.read_timeout,
+            idle: false,
+            after: limits.read_timeout,
+        }
+    }
+
+    fn expired(&self) -> CloseReason {
+        if self.idle {
+            CloseReason::IdleTimeout(self.after)
+        } else {
+            CloseReason::ReadTimeout(self.after)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+    use tokio_util::codec::LinesCodec;
+
+    async fn pair(limits: Limits) -> (Connection, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (server, peer) = listener.accept().await.unwrap();
+        (Connection::new(server, peer, limits), client)
+    }
+
+    #[tokio::test]
+    async fn timeouts_lose_nothing() {
+        let limits = Limits {
+            idle_timeout: Duration::from_millis(50),
+            read_timeout: Duration::from_millis(50),
+            ..Limits::default()
+        };
+        let (mut connection, mut client) = pair(limits).await;
+        let mut lines = LinesCodec::new();
+        assert!(matches!(
+            connection.read_frame(&mut lines).await,
+            Err(CloseReason::IdleTimeout(_))
+        ));
+
+        client.write_all(b"hel").await.unwrap();
+        assert!(matches!(
+            connection.read_frame(&mut lines).await,
+            Err(CloseReason::ReadTimeout(_))
+        ));
+        client.write_all(b"lo\nwor").await.unwrap();
+        assert_eq!(connection.read_frame(&mut lines).await.unwrap(), "hello");
+
+        client.write_all(b"ld").await.unwrap();
+        client.shutdown().await.unwrap();
+        assert_eq!(connection.read_frame(&mut lines).await.unwrap(), "world");
+        assert!(matches!(
+            connection.read_frame(&mut lines).await,
+            Err(CloseReason::Hangup)
+        ));
+    }
+
+    #[tokio::test]
+    async fn requests_are_bounded() {
+        let limits = Limits {
+            max_request_size: 8,
+            ..Limits::default()
+        };
+        let (mut connection, mut client) = pair(limits).await;
+        client.write_all(b"12345678\n123456789").await.unwrap();
+        let mut lines = LinesCodec::new();
+        assert_eq!(connection.read_frame(&mut lines).await.unwrap(), "12345678");
+        assert!(matches!(
+            connection.read_frame(&mut lines).await,
+            Err(CloseReason::RequestTooLarge(8))
+        ));
+    }
+
+    #[tokio::test]
+    async fn requests_are_bounded_when_they_arrive_in_one_go() {
+        let limits = Limits {
+            max_request_size: 8,
+            ..Limits::default()
+        };
+        let (mut connection, mut client) = pair(limits).await;
+        client.write_all(b"1234567890123\n").await.unwrap();
+        assert!(matches!(
+            connection.read_frame(&mut LinesCodec::new()).await,
+            Err(CloseReason::RequestTooLarge(8))
+        ));
+
+        // The decoder's own limit doesn't change the outcome.
+        let (mut connection, mut client) = pair(limits).await;
+        client.write_all(b"1234567890123\n").await.unwrap();
+        assert!(matches!(
+            connection
+                .read_frame(&mut LinesCodec::new_with_max_length(8))
+                .await,
+            Err(CloseReason::RequestTooLarge(8))
+        ));
+    }
+}
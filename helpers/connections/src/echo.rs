@@ -0,0 +1,31 @@
+//! The echo servers from `08_futures/01_async_fn` and `08_futures/02_spawn`,
+//! on a [`ConnectionManager`].
+//!
+//! A request is everything a client sends until it shuts its end down: it's
+//! sent back as is, then the connection is closed. A request that's too large
+//! or too slow to arrive gets no reply.
+use crate::{CloseReason, Connection, ConnectionManager};
+use tokio::net::TcpListener;
+
+pub async fn echo(listener: TcpListener, manager: &ConnectionManager) {
+    manager.serve(listener, echo_connection).await
+}
+
+/// Serve both listeners at once, under the same connection limit.
+pub async fn echoes(first: TcpListener, second: TcpListener, manager: &ConnectionManager) {
+    tokio::join!(
+        manager.serve(first, echo_connection),
+        manager.serve(second, echo_connection)
+    );
+}
+
+async fn echo_connection(mut connection: Connection) {
+    let reason = match connection.read_to_end().await {
+        Ok(request) => match connection.write_all(&request).await {
+            Ok(()) => CloseReason::Hangup,
+            Err(reason) => reason,
+        },
+        Err(reason) => reason,
+    };
+    connection.close(reason).await;
+}
@@ -0,0 +1,115 @@
+//! Serving TCP connections without letting clients exhaust the server.
+//!
+//! Spawning a task for every accepted connection, with no bound, lets any
+//! number of clients hold on to a task and a socket each, for as long as they
+//! like, sending requests as large as they like. A [`ConnectionManager`] caps
+//! all of that: see [`Limits`].
+//!
+//! Every way a connection can end is a [`CloseReason`], rather than a silent
+//! drop: a timeout on a read doesn't throw away what was read so far without
+//! telling anyone (see `08_futures/07_cancellation`).
+mod connection;
+pub mod echo;
+
+pub use connection::{CloseReason, Connection};
+
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::Semaphore;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    /// Past it, no more connections are accepted until one closes:
+    /// new clients wait in the listener's backlog.
+    pub max_connections: usize,
+    /// How long a connection may go without starting a request.
+    pub idle_timeout: Duration,
+    /// How long a request may take to arrive in full, once it started.
+    pub read_timeout: Duration,
+    /// The largest request allowed, in bytes.
+    pub max_request_size: usize,
+    /// How long to wait for the client to hang up once the server closed its
+    /// end, so that whatever it sent last doesn't turn the close into a reset.
+    pub linger: Duration,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_connections: 1024,
+            idle_timeout: Duration::from_secs(60),
+            read_timeout: Duration::from_secs(10),
+            max_request_size: 64 * 1024,
+            linger: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Accepts connections within [`Limits`]. Cloning it is cheap, and the clones
+/// share the same connection count: a server can accept on several listeners
+/// under a single cap.
+#[derive(Clone)]
+pub struct ConnectionManager {
+    limits: Limits,
+    permits: Arc<Semaphore>,
+}
+
+impl ConnectionManager {
+    pub fn new(limits: Limits) -> Self {
+        Self {
+            limits,
+            permits: Arc::new(Semaphore::new(limits.max_connections)),
+        }
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// How many connections are open.
+    pub fn active(&self) -> usize {
+        self.limits.max_connections - self.permits.available_permits()
+    }
+
+    /// Accept connections on `listener` forever, each served by `handle` on a
+    /// task of its own.
+    ///
+    /// Errors while accepting are logged, and accepting carries on.
+    pub async fn serve<H, F>(&self, listener: TcpListener, handle: H)
+    where
+        H: Fn(Connection) -> F,
+        F: Future<Output = ()> + Send + 'static,
+    {
+        loop {
+            let permit = Arc::clone(&self.permits)
+                .acquire_owned()
+                .await
+                .expect("The semaphore is never closed");
+            let (stream, peer) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    tracing::warn!(%e, "Failed to accept a connection");
+                    // E.g. out of file descriptors: give the others a chance to close.
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                }
+            };
+            let connection = Connection::new(stream, peer, self.limits);
+            let task = handle(connection);
+            tokio::spawn(async move {
+                task.await;
+                drop(permit);
+            });
+        }
+    }
+}
+
+pub(crate) fn log_close(peer: SocketAddr, reason: &CloseReason) {
+    match reason {
+        CloseReason::Io(e) => tracing::warn!(%peer, %e, "Connection failed"),
+        reason => tracing::debug!(%peer, %reason, "Connection closed"),
+    }
+}
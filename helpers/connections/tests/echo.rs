@@ -0,0 +1,129 @@
+use connections::echo::{echo, echoes};
+use connections::{ConnectionManager, Limits};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinSet;
+
+async fn bind_random() -> (TcpListener, SocketAddr) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    (listener, addr)
+}
+
+fn limits() -> Limits {
+    Limits {
+        max_connections: 4,
+        idle_timeout: Duration::from_millis(200),
+        read_timeout: Duration::from_millis(200),
+        max_request_size: 16,
+        linger: Duration::from_millis(50),
+    }
+}
+
+async fn start(limits: Limits) -> (ConnectionManager, SocketAddr) {
+    let (listener, addr) = bind_random().await;
+    let manager = ConnectionManager::new(limits);
+    tokio::spawn({
+        let manager = manager.clone();
+        async move { echo(listener, &manager).await }
+    });
+    (manager, addr)
+}
+
+/// Send `request`, shut the write side down, and read the reply to the end.
+async fn request(addr: SocketAddr, request: &[u8]) -> Vec<u8> {
+    let mut socket = TcpStream::connect(addr).await.unwrap();
+    socket.write_all(request).await.unwrap();
+    socket.shutdown().await.unwrap();
+    let mut reply = Vec::new();
+    socket.read_to_end(&mut reply).await.unwrap();
+    reply
+}
+
+/// Wait for the manager to have accepted `n` connections: it happens
+/// some time after the clients connect.
+async fn until_active(manager: &ConnectionManager, n: usize) {
+    let accepted = async {
+        while manager.active() < n {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(10), accepted)
+        .await
+        .expect("The connections weren't accepted");
+}
+
+#[tokio::test]
+async fn echoes_requests() {
+    let (_manager, addr) = start(limits()).await;
+    for message in ["hello", "world", "foo", "bar"] {
+        assert_eq!(request(addr, message.as_bytes()).await, message.as_bytes());
+    }
+}
+
+#[tokio::test]
+async fn echoes_on_two_listeners() {
+    let (first, first_addr) = bind_random().await;
+    let (second, second_addr) = bind_random().await;
+    tokio::spawn(async move { echoes(first, second, &ConnectionManager::new(limits())).await });
+
+    let mut join_set = JoinSet::new();
+    for message in ["hello", "world", "foo", "bar"] {
+        for addr in [first_addr, second_addr] {
+            join_set.spawn(async move { (message, request(addr, message.as_bytes()).await) });
+        }
+    }
+    while let Some(outcome) = join_set.join_next().await {
+        let (message, reply) = outcome.unwrap();
+        assert_eq!(reply, message.as_bytes());
+    }
+}
+
+#[tokio::test]
+async fn large_requests_get_no_reply() {
+    let (_manager, addr) = start(limits()).await;
+    assert_eq!(request(addr, &[b'x'; 64]).await, b"");
+    // The connection was closed, not the server.
+    assert_eq!(request(addr, b"small").await, b"small");
+}
+
+#[tokio::test]
+async fn slow_requests_get_no_reply() {
+    let (_manager, addr) = start(limits()).await;
+
+    // Never sends anything.
+    let mut idle = TcpStream::connect(addr).await.unwrap();
+    let mut reply = Vec::new();
+    idle.read_to_end(&mut reply).await.unwrap();
+    assert!(reply.is_empty());
+
+    // Starts, but never finishes.
+    let mut slow = TcpStream::connect(addr).await.unwrap();
+    slow.write_all(b"hel").await.unwrap();
+    slow.read_to_end(&mut reply).await.unwrap();
+    assert!(reply.is_empty());
+}
+
+#[tokio::test]
+async fn connections_beyond_the_limit_wait() {
+    let (manager, addr) = start(Limits {
+        max_connections: 2,
+        idle_timeout: Duration::from_secs(10),
+        ..limits()
+    })
+    .await;
+    let first = TcpStream::connect(addr).await.unwrap();
+    let _second = TcpStream::connect(addr).await.unwrap();
+    until_active(&manager, 2).await;
+
+    // Connected, as far as the OS is concerned, but not served yet.
+    let third = tokio::spawn(request(addr, b"third"));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!third.is_finished());
+    assert_eq!(manager.active(), 2);
+
+    drop(first);
+    assert_eq!(third.await.unwrap(), b"third");
+}
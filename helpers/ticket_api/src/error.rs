@@ -0,0 +1,68 @@
+use async_ticket_server::ClientError;
+use axum::extract::rejection::{JsonRejection, PathRejection, QueryRejection};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+use ticket_fields::{TicketDescriptionError, TicketTitleError};
+use utoipa::ToSchema;
+
+/// Why a request failed.
+///
+/// A ticket that doesn't exist is a `404`, a title or description that doesn't
+/// pass validation a `422`, and a store that's overloaded or gone a `503`.
+/// Requests that can't be parsed get whatever axum picked for them: usually
+/// `400`, or `422` for well-formed JSON of the wrong shape.
+#[derive(Debug, thiserror::Error)]
+pub enum ApiError {
+    #[error("There is no ticket with id {0}")]
+    NotFound(u64),
+    #[error(transparent)]
+    Title(#[from] TicketTitleError),
+    #[error(transparent)]
+    Description(#[from] TicketDescriptionError),
+    #[error(transparent)]
+    Store(#[from] ClientError),
+    #[error("{message}")]
+    Rejected { status: StatusCode, message: String },
+}
+
+impl ApiError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Title(_) | ApiError::Description(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::Store(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::Rejected { status, .. } => *status,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct ErrorBody {
+    error: String,
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = ErrorBody {
+            error: self.to_string(),
+        };
+        (self.status(), Json(body)).into_response()
+    }
+}
+
+macro_rules! impl_from_rejection {
+    ($($rejection:ty),*) => {
+        $(impl From<$rejection> for ApiError {
+            fn from(rejection: $rejection) -> Self {
+                ApiError::Rejected {
+                    status: rejection.status(),
+                    message: rejection.body_text(),
+                }
+            }
+        })*
+    };
+}
+
+impl_from_rejection!(JsonRejection, PathRejection, QueryRejection);
@@ -0,0 +1,63 @@
+//! A REST API for the ticket store, as sketched in `08_futures/08_outro`.
+//!
+//! | Method  | Path            |                                          |
+//! |---------|-----------------|------------------------------------------|
+//! | `POST`  | `/tickets`      | Create a ticket from a [`TicketDraft`]   |
+//! | `GET`   | `/tickets`      | List tickets, filtered by [`ListQuery`]  |
+//! | `GET`   | `/tickets/{id}` | Retrieve a ticket                        |
+//! | `PATCH` | `/tickets/{id}` | Update a ticket with a [`TicketPatch`]   |
+//!
+//! Bodies are JSON. Errors come back as `{"error": "<message>"}`: see [`ApiError`]
+//! for the status codes.
+//!
+//! An OpenAPI 3 description of all of the above is served at `/openapi.json`.
+//! It's generated from the handlers and the types they take and return:
+//! see [`openapi`].
+mod error;
+mod model;
+mod routes;
+
+pub use error::ApiError;
+pub use model::{ListQuery, Status, Ticket, TicketDraft, TicketPatch};
+
+use async_ticket_server::TicketStoreClient;
+use axum::routing::get;
+use axum::{Json, Router};
+use tokio::net::TcpListener;
+use utoipa::OpenApi;
+use utoipa_axum::router::OpenApiRouter;
+use utoipa_axum::routes;
+
+#[derive(OpenApi)]
+#[openapi(info(
+    title = "Tickets",
+    description = "Create, retrieve and update tickets."
+))]
+struct ApiDoc;
+
+/// The routes, each registered along with its description: they can't drift apart.
+fn routes() -> OpenApiRouter<TicketStoreClient> {
+    let mut doc = ApiDoc::openapi();
+    // Taken from the manifest, which doesn't have one.
+    doc.info.license = None;
+    OpenApiRouter::with_openapi(doc)
+        .routes(routes!(routes::create, routes::list))
+        .routes(routes!(routes::retrieve, routes::update))
+}
+
+/// The OpenAPI description of the API, as served at `/openapi.json`.
+pub fn openapi() -> utoipa::openapi::OpenApi {
+    routes().split_for_parts().1
+}
+
+pub fn router(client: TicketStoreClient) -> Router {
+    let (router, openapi) = routes().split_for_parts();
+    router
+        .route("/openapi.json", get(|| async move { Json(openapi) }))
+        .with_state(client)
+}
+
+/// Serve the API on `listener`, forwarding requests to the store behind `client`.
+pub async fn serve(listener: TcpListener, client: TicketStoreClient) -> std::io::Result<()> {
+    axum::serve(listener, router(client)).await
+}
@@ -0,0 +1,212 @@
+//! The tickets as they go over the wire.
+//!
+//! Titles and descriptions come in as plain strings: they're validated on the
+//! way to the store, so that a bad one is reported as such rather than as a
+//! malformed body.
+use crate::ApiError;
+use async_ticket_server::data;
+use async_ticket_server::TicketFilter;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+use ticket_fields::{TicketDescription, TicketTitle};
+use utoipa::openapi::schema::{Object, ObjectBuilder, SchemaType, Type};
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum Status {
+    #[serde(rename = "todo")]
+    ToDo,
+    #[serde(rename = "in_progress")]
+    InProgress,
+    #[serde(rename = "done")]
+    Done,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Ticket {
+    pub id: u64,
+    #[schema(schema_with = title)]
+    pub title: String,
+    #[schema(schema_with = description)]
+    pub description: String,
+    pub status: Status,
+    /// Seconds since the Unix epoch.
+    pub created_at: u64,
+    pub resolved_at: Option<u64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TicketDraft {
+    #[schema(schema_with = title)]
+    pub title: String,
+    #[schema(schema_with = description)]
+    pub description: String,
+}
+
+/// The fields to change. The ones left out are kept as they are.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct TicketPatch {
+    #[schema(schema_with = optional_title)]
+    pub title: Option<String>,
+    #[schema(schema_with = optional_description)]
+    pub description: Option<String>,
+    pub status: Option<Status>,
+}
+
+/// The query string of `GET /tickets`, e.g. `?status=todo&title=login`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, IntoParams)]
+#[serde(default, deny_unknown_fields)]
+#[into_params(parameter_in = Query)]
+pub struct ListQuery {
+    pub status: Option<Status>,
+    /// Only the tickets whose title contains it, ignoring case.
+    pub title: Option<String>,
+}
+
+// The length limits are the ones `ticket_fields` enforces. They count bytes,
+// while `maxLength` counts characters: the description tells the difference.
+
+/// A title or description. In a patch it's `nullable`: `null` keeps the field as it is.
+fn text(max_length: usize, nullable: bool) -> Object {
+    let schema_type = if nullable {
+        SchemaType::from_iter([Type::String, Type::Null])
+    } else {
+        SchemaType::from(Type::String)
+    };
+    ObjectBuilder::new()
+        .schema_type(schema_type)
+        .min_length(Some(1))
+        .max_length(Some(max_length))
+        .description(Some(format!(
+            "At most {max_length} bytes once encoded as UTF-8."
+        )))
+        .build()
+}
+
+fn title() -> Object {
+    text(TicketTitle::MAX_LENGTH, false)
+}
+
+fn description() -> Object {
+    text(TicketDescription::MAX_LENGTH, false)
+}
+
+fn optional_title() -> Object {
+    text(TicketTitle::MAX_LENGTH, true)
+}
+
+fn optional_description() -> Object {
+    text(TicketDescription::MAX_LENGTH, true)
+}
+
+impl From<data::Status> for Status {
+    fn from(status: data::Status) -> Self {
+        match status {
+            data::Status::ToDo => Status::ToDo,
+            data::Status::InProgress => Status::InProgress,
+            data::Status::Done => Status::Done,
+        }
+    }
+}
+
+impl From<Status> for data::Status {
+    fn from(status: Status) -> Self {
+        match status {
+            Status::ToDo => data::Status::ToDo,
+            Status::InProgress => data::Status::InProgress,
+            Status::Done => data::Status::Done,
+        }
+    }
+}
+
+fn seconds(at: SystemTime) -> u64 {
+    at.duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+impl From<data::Ticket> for Ticket {
+    fn from(ticket: data::Ticket) -> Self {
+        Self {
+            id: ticket.id.into(),
+            title: ticket.title.as_str().to_owned(),
+            description: ticket.description.as_str().to_owned(),
+            status: ticket.status.into(),
+            created_at: seconds(ticket.created_at),
+            resolved_at: ticket.resolved_at.map(seconds),
+        }
+    }
+}
+
+impl TryFrom<TicketDraft> for data::TicketDraft {
+    type Error = ApiError;
+
+    fn try_from(draft: TicketDraft) -> Result<Self, Self::Error> {
+        Ok(Self {
+            title: TicketTitle::try_from(draft.title)?,
+            description: TicketDescription::try_from(draft.description)?,
+        })
+    }
+}
+
+impl TicketPatch {
+    pub(crate) fn validate(self, id: data::TicketId) -> Result<data::TicketPatch, ApiError> {
+        Ok(data::TicketPatch {
+            id,
+            title: self.title.map(TicketTitle::try_from).transpose()?,
+            description: self
+                .description
+                .map(TicketDescription::try_from)
+                .transpose()?,
+            status: self.status.map(Into::into),
+        })
+    }
+}
+
+impl From<ListQuery> for TicketFilter {
+    fn from(query: ListQuery) -> Self {
+        Self {
+            status: query.status.map(Into::into),
+            title: query.title,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn statuses_round_trip() {
+        for status in data::Status::ALL {
+            let json = serde_json::to_value(Status::from(status)).unwrap();
+            let back: Status = serde_json::from_value(json).unwrap();
+            assert_eq!(data::Status::from(back), status);
+        }
+        assert_eq!(
+            serde_json::to_value(Status::InProgress).unwrap(),
+            json!("in_progress")
+        );
+    }
+
+    #[test]
+    fn drafts_are_validated() {
+        let draft = TicketDraft {
+            title: String::new(),
+            description: "A description".into(),
+        };
+        let error = data::TicketDraft::try_from(draft).unwrap_err();
+        assert_eq!(error.to_string(), "The title cannot be empty");
+    }
+
+    #[test]
+    fn patches_keep_what_is_left_out() {
+        let patch: TicketPatch = serde_json::from_value(json!({"status": "done"})).unwrap();
+        let patch = patch.validate(data::TicketId::from(3)).unwrap();
+        assert_eq!(patch.title, None);
+        assert_eq!(patch.description, None);
+        assert_eq!(patch.status, Some(data::Status::Done));
+    }
+}
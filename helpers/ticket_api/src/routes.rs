@@ -0,0 +1,117 @@
+use crate::error::ErrorBody;
+use crate::model::{ListQuery, Ticket, TicketDraft, TicketPatch};
+use crate::ApiError;
+use async_ticket_server::data::{self, TicketId};
+use async_ticket_server::TicketStoreClient;
+use axum::extract::rejection::{JsonRejection, PathRejection, QueryRejection};
+use axum::extract::{Path, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::IntoResponse;
+use axum::Json;
+
+// Extractors are taken as `Result`s, so that a bad request gets the same
+// kind of error body as everything else.
+// The annotations are what `/openapi.json` is generated from: keep the
+// documented responses in sync with what the handlers can return.
+
+/// Create a ticket.
+#[utoipa::path(
+    post,
+    path = "/tickets",
+    request_body = TicketDraft,
+    responses(
+        (status = 201, description = "The new ticket", body = Ticket,
+            headers(("location" = String, description = "The path of the new ticket"))),
+        (status = 400, description = "The body isn't valid JSON", body = ErrorBody),
+        (status = 415, description = "The body isn't marked as JSON", body = ErrorBody),
+        (status = 422, description = "The title or description is invalid", body = ErrorBody),
+        (status = 503, description = "The store is overloaded", body = ErrorBody),
+    )
+)]
+pub(crate) async fn create(
+    State(client): State<TicketStoreClient>,
+    draft: Result<Json<TicketDraft>, JsonRejection>,
+) -> Result<impl IntoResponse, ApiError> {
+    let Json(draft) = draft?;
+    let ticket = Ticket::from(client.create(data::TicketDraft::try_from(draft)?).await?);
+    let location = format!("/tickets/{}", ticket.id);
+    Ok((
+        StatusCode::CREATED,
+        [(header::LOCATION, location)],
+        Json(ticket),
+    ))
+}
+
+/// Retrieve a ticket.
+#[utoipa::path(
+    get,
+    path = "/tickets/{id}",
+    params(("id" = u64, Path, description = "The id of the ticket")),
+    responses(
+        (status = 200, description = "The ticket", body = Ticket),
+        (status = 400, description = "The id isn't a number", body = ErrorBody),
+        (status = 404, description = "There is no such ticket", body = ErrorBody),
+        (status = 503, description = "The store is overloaded", body = ErrorBody),
+    )
+)]
+pub(crate) async fn retrieve(
+    State(client): State<TicketStoreClient>,
+    id: Result<Path<u64>, PathRejection>,
+) -> Result<Json<Ticket>, ApiError> {
+    let Path(id) = id?;
+    Ok(Json(find(&client, id.into()).await?))
+}
+
+/// Update some of the fields of a ticket.
+#[utoipa::path(
+    patch,
+    path = "/tickets/{id}",
+    params(("id" = u64, Path, description = "The id of the ticket")),
+    request_body = TicketPatch,
+    responses(
+        (status = 200, description = "The updated ticket", body = Ticket),
+        (status = 400, description = "The id isn't a number, or the body isn't valid JSON", body = ErrorBody),
+        (status = 404, description = "There is no such ticket", body = ErrorBody),
+        (status = 415, description = "The body isn't marked as JSON", body = ErrorBody),
+        (status = 422, description = "A field is invalid", body = ErrorBody),
+        (status = 503, description = "The store is overloaded", body = ErrorBody),
+    )
+)]
+pub(crate) async fn update(
+    State(client): State<TicketStoreClient>,
+    id: Result<Path<u64>, PathRejection>,
+    patch: Result<Json<TicketPatch>, JsonRejection>,
+) -> Result<Json<Ticket>, ApiError> {
+    let Path(id) = id?;
+    let Json(patch) = patch?;
+    let patch = patch.validate(id.into())?;
+    let ticket = client.update(patch).await?.ok_or(ApiError::NotFound(id))?;
+    Ok(Json(ticket.into()))
+}
+
+/// List the tickets that match the query, in id order.
+#[utoipa::path(
+    get,
+    path = "/tickets",
+    params(ListQuery),
+    responses(
+        (status = 200, description = "The matching tickets", body = Vec<Ticket>),
+        (status = 400, description = "The query is invalid", body = ErrorBody),
+        (status = 503, description = "The store is overloaded", body = ErrorBody),
+    )
+)]
+pub(crate) async fn list(
+    State(client): State<TicketStoreClient>,
+    query: Result<Query<ListQuery>, QueryRejection>,
+) -> Result<Json<Vec<Ticket>>, ApiError> {
+    let Query(query) = query?;
+    let tickets = client.list(query.into()).await?;
+    Ok(Json(tickets.into_iter().map(Ticket::from).collect()))
+}
+
+async fn find(client: &TicketStoreClient, id: TicketId) -> Result<Ticket, ApiError> {
+    let ticket = client.get(id).await?;
+    ticket
+        .map(Ticket::from)
+        .ok_or(ApiError::NotFound(id.into()))
+}
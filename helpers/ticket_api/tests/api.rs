@@ -0,0 +1,200 @@
+use async_ticket_server::launch;
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+use ticket_api::{serve, Status, Ticket};
+use tokio::net::TcpListener;
+
+/// Start the API on a random port, returning its base URL.
+async fn start() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(serve(listener, launch(16)));
+    format!("http://{addr}")
+}
+
+async fn create(client: &Client, base: &str, title: &str) -> Ticket {
+    let response = client
+        .post(format!("{base}/tickets"))
+        .json(&json!({"title": title, "description": "A description"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    response.json().await.unwrap()
+}
+
+async fn error(response: reqwest::Response) -> (StatusCode, String) {
+    let status = response.status();
+    let body: Value = response.json().await.unwrap();
+    (status, body["error"].as_str().unwrap().to_owned())
+}
+
+#[tokio::test]
+async fn create_retrieve_and_patch() {
+    let base = start().await;
+    let client = Client::new();
+
+    let response = client
+        .post(format!("{base}/tickets"))
+        .json(&json!({"title": "A title", "description": "A description"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let location = response.headers()["location"].to_str().unwrap().to_owned();
+    let created: Ticket = response.json().await.unwrap();
+    assert_eq!(location, format!("/tickets/{}", created.id));
+    assert_eq!(created.title, "A title");
+    assert_eq!(created.status, Status::ToDo);
+    assert_eq!(created.resolved_at, None);
+
+    let retrieved: Ticket = client
+        .get(format!("{base}{location}"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(retrieved, created);
+
+    let response = client
+        .patch(format!("{base}{location}"))
+        .json(&json!({"status": "done"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let patched: Ticket = response.json().await.unwrap();
+    assert_eq!(patched.status, Status::Done);
+    assert_eq!(patched.title, created.title);
+    assert!(patched.resolved_at.is_some());
+}
+
+#[tokio::test]
+async fn missing_tickets_are_404() {
+    let base = start().await;
+    let client = Client::new();
+
+    let response = client
+        .get(format!("{base}/tickets/7"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        error(response).await,
+        (StatusCode::NOT_FOUND, "There is no ticket with id 7".into())
+    );
+    let response = client
+        .patch(format!("{base}/tickets/7"))
+        .json(&json!({"title": "New title"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn invalid_fields_are_422() {
+    let base = start().await;
+    let client = Client::new();
+
+    let response = client
+        .post(format!("{base}/tickets"))
+        .json(&json!({"title": "", "description": "A description"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        error(response).await,
+        (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "The title cannot be empty".into()
+        )
+    );
+
+    let ticket = create(&client, &base, "A title").await;
+    let response = client
+        .patch(format!("{base}/tickets/{}", ticket.id))
+        .json(&json!({"description": "x".repeat(501)}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        error(response).await,
+        (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "The description cannot be longer than 500 bytes".into()
+        )
+    );
+}
+
+#[tokio::test]
+async fn malformed_requests_are_rejected() {
+    let base = start().await;
+    let client = Client::new();
+
+    let response = client
+        .post(format!("{base}/tickets"))
+        .header("content-type", "application/json")
+        .body("{")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(error(response).await.0, StatusCode::BAD_REQUEST);
+
+    let response = client
+        .patch(format!("{base}/tickets/0"))
+        .json(&json!({"status": "closed"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(error(response).await.0, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let response = client
+        .get(format!("{base}/tickets/first"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(error(response).await.0, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn list_with_filters() {
+    let base = start().await;
+    let client = Client::new();
+    let login = create(&client, &base, "Fix the login page").await;
+    create(&client, &base, "Write the docs").await;
+    create(&client, &base, "Log in with SSO").await;
+    client
+        .patch(format!("{base}/tickets/{}", login.id))
+        .json(&json!({"status": "in_progress"}))
+        .send()
+        .await
+        .unwrap();
+
+    let list = |query: &'static str| {
+        let request = client.get(format!("{base}/tickets{query}"));
+        async move {
+            let tickets: Vec<Ticket> = request.send().await.unwrap().json().await.unwrap();
+            tickets
+                .into_iter()
+                .map(|ticket| ticket.title)
+                .collect::<Vec<_>>()
+        }
+    };
+    assert_eq!(list("").await.len(), 3);
+    assert_eq!(list("?status=in_progress").await, ["Fix the login page"]);
+    assert_eq!(
+        list("?title=LOG").await,
+        ["Fix the login page", "Log in with SSO"]
+    );
+    assert_eq!(list("?status=todo&title=log").await, ["Log in with SSO"]);
+
+    let response = client
+        .get(format!("{base}/tickets?status=closed"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(error(response).await.0, StatusCode::BAD_REQUEST);
+}
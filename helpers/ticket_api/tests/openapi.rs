@@ -0,0 +1,149 @@
+use async_ticket_server::launch;
+use reqwest::{Client, Method, Response};
+use serde_json::{json, Value};
+use std::path::Path;
+use ticket_api::{openapi, serve};
+use ticket_fields::{TicketDescription, TicketTitle};
+use tokio::net::TcpListener;
+
+fn spec() -> Value {
+    serde_json::to_value(openapi()).unwrap()
+}
+
+/// The spec is checked in next to the crate, for the frontend to build on.
+/// Run with `UPDATE_OPENAPI=1` to refresh it after changing the API.
+#[test]
+fn checked_in_spec_is_up_to_date() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("openapi.json");
+    let generated = openapi().to_pretty_json().unwrap() + "\n";
+    if std::env::var_os("UPDATE_OPENAPI").is_some() {
+        std::fs::write(&path, &generated).unwrap();
+    }
+    let checked_in = std::fs::read_to_string(&path).unwrap_or_default();
+    assert!(
+        checked_in == generated,
+        "openapi.json is out of date: run `UPDATE_OPENAPI=1 cargo test -p ticket_api --test openapi`"
+    );
+}
+
+#[test]
+fn constraints_come_from_the_types() {
+    let spec = spec();
+    let schemas = &spec["components"]["schemas"];
+    for schema in ["Ticket", "TicketDraft", "TicketPatch"] {
+        let properties = &schemas[schema]["properties"];
+        assert_eq!(properties["title"]["minLength"], 1);
+        assert_eq!(properties["title"]["maxLength"], TicketTitle::MAX_LENGTH);
+        assert_eq!(properties["description"]["minLength"], 1);
+        assert_eq!(
+            properties["description"]["maxLength"],
+            TicketDescription::MAX_LENGTH
+        );
+    }
+    assert_eq!(
+        schemas["Status"]["enum"],
+        json!(["todo", "in_progress", "done"])
+    );
+    assert_eq!(
+        schemas["TicketDraft"]["required"],
+        json!(["title", "description"])
+    );
+    assert!(schemas["TicketPatch"]["required"].is_null());
+    let patch = &schemas["TicketPatch"]["properties"];
+    assert_eq!(patch["title"]["type"], json!(["string", "null"]));
+    assert_eq!(patch["description"]["type"], json!(["string", "null"]));
+}
+
+/// Start the API on a random port, returning its base URL.
+async fn start() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(serve(listener, launch(16)));
+    format!("http://{addr}")
+}
+
+/// Check `response` against what the spec says `method path` returns:
+/// its status must be documented, and its body must have the documented fields.
+async fn conforms(spec: &Value, method: &Method, path: &str, response: Response) -> Value {
+    let operation = &spec["paths"][path][method.as_str().to_lowercase()];
+    assert!(operation.is_object(), "{method} {path} isn't documented");
+    let status = response.status();
+    let documented = &operation["responses"][status.as_str()];
+    assert!(
+        documented.is_object(),
+        "{method} {path} returned {status}, which isn't documented"
+    );
+    let body: Value = response.json().await.unwrap();
+    let mut schema = &documented["content"]["application/json"]["schema"];
+    let mut item = &body;
+    if schema["type"] == "array" {
+        schema = &schema["items"];
+        item = &body[0];
+    }
+    if let (Some(reference), Some(fields)) = (schema["$ref"].as_str(), item.as_object()) {
+        let name = reference.trim_start_matches("#/components/schemas/");
+        let properties = spec["components"]["schemas"][name]["properties"]
+            .as_object()
+            .unwrap();
+        let mut expected: Vec<_> = properties.keys().collect();
+        let mut actual: Vec<_> = fields.keys().collect();
+        expected.sort();
+        actual.sort();
+        assert_eq!(
+            actual, expected,
+            "{method} {path} returned a different {name}"
+        );
+    }
+    body
+}
+
+#[tokio::test]
+async fn handlers_behave_as_documented() {
+    let base = start().await;
+    let client = Client::new();
+    let spec = spec();
+
+    let served: Value = client
+        .get(format!("{base}/openapi.json"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(served, spec);
+
+    let draft = json!({"title": "A title", "description": "A description"});
+    let invalid = json!({"title": "", "description": "A description"});
+    let cases = [
+        (Method::POST, "/tickets", "/tickets", Some(draft)),
+        (Method::POST, "/tickets", "/tickets", Some(invalid.clone())),
+        (Method::POST, "/tickets", "/tickets", None),
+        (Method::GET, "/tickets/{id}", "/tickets/0", None),
+        (Method::GET, "/tickets/{id}", "/tickets/42", None),
+        (Method::GET, "/tickets/{id}", "/tickets/first", None),
+        (
+            Method::PATCH,
+            "/tickets/{id}",
+            "/tickets/0",
+            Some(json!({"status": "done"})),
+        ),
+        (Method::PATCH, "/tickets/{id}", "/tickets/0", Some(invalid)),
+        (
+            Method::PATCH,
+            "/tickets/{id}",
+            "/tickets/42",
+            Some(json!({})),
+        ),
+        (Method::GET, "/tickets", "/tickets?status=done", None),
+        (Method::GET, "/tickets", "/tickets?status=closed", None),
+    ];
+    for (method, template, path, body) in cases {
+        let mut request = client.request(method.clone(), format!("{base}{path}"));
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        let response = request.send().await.unwrap();
+        conforms(&spec, &method, template, response).await;
+    }
+}
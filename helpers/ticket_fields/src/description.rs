@@ -1,7 +1,7 @@
 #[derive(Debug, PartialEq, Clone, Eq)]
 pub struct TicketDescription(String);
 
-#[derive(Debug, thiserror::Error)]
+#[derive(Debug, PartialEq, Clone, Eq, thiserror::Error)]
 pub enum TicketDescriptionError {
     #[error("The description cannot be empty")]
     Empty,
@@ -9,6 +9,15 @@ pub enum TicketDescriptionError {
     TooLong,
 }
 
+impl TicketDescription {
+    /// The longest description allowed, in bytes.
+    pub const MAX_LENGTH: usize = 500;
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
 impl TryFrom<String> for TicketDescription {
     type Error = TicketDescriptionError;
 
@@ -30,7 +39,7 @@ impl TryFrom<&str> for TicketDescription {
 fn validate(description: &str) -> Result<(), TicketDescriptionError> {
     if description.is_empty() {
         Err(TicketDescriptionError::Empty)
-    } else if description.len() > 500 {
+    } else if description.len() > TicketDescription::MAX_LENGTH {
         Err(TicketDescriptionError::TooLong)
     } else {
         Ok(())
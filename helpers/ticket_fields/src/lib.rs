@@ -2,5 +2,5 @@ mod description;
 pub mod test_helpers;
 mod title;
 
-pub use description::TicketDescription;
-pub use title::TicketTitle;
+pub use description::{TicketDescription, TicketDescriptionError};
+pub use title::{TicketTitle, TicketTitleError};
@@ -3,7 +3,7 @@ use std::convert::TryFrom;
 #[derive(Debug, PartialEq, Clone, Eq)]
 pub struct TicketTitle(String);
 
-#[derive(Debug, thiserror::Error)]
+#[derive(Debug, PartialEq, Clone, Eq, thiserror::Error)]
 pub enum TicketTitleError {
     #[error("The title cannot be empty")]
     Empty,
@@ -11,6 +11,15 @@ pub enum TicketTitleError {
     TooLong,
 }
 
+impl TicketTitle {
+    /// The longest title allowed, in bytes.
+    pub const MAX_LENGTH: usize = 50;
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
 impl TryFrom<String> for TicketTitle {
     type Error = TicketTitleError;
 
@@ -32,7 +41,7 @@ impl TryFrom<&str> for TicketTitle {
 fn validate(title: &str) -> Result<(), TicketTitleError> {
     if title.is_empty() {
         Err(TicketTitleError::Empty)
-    } else if title.len() > 50 {
+    } else if title.len() > TicketTitle::MAX_LENGTH {
         Err(TicketTitleError::TooLong)
     } else {
         Ok(())
@@ -0,0 +1,353 @@
+use crate::events::{EventFilter, Subscription};
+use crate::lanes::{Lanes, Priority};
+use crate::metrics::Stats;
+use crate::server::{
+    Batch, BatchOp, BatchResponse, Command, Get, GetStats, Insert, ServerData, ServerState,
+    Subscribe, Update,
+};
+use actor::{ActorError, Envelope, Handler, Message, Pending, ReadHandler};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{sync_channel, TrySendError};
+use std::sync::Arc;
+use std::time::Duration;
+use ticket_store::data::{Ticket, TicketDraft, TicketId, TicketPatch};
+
+/// A handle to talk to the server. Cloning it is cheap.
+///
+/// Every call waits at most [`TicketStoreClient::DEFAULT_TIMEOUT`] for the reply.
+/// Use [`with_timeout`](Self::with_timeout), [`with_send_mode`](Self::with_send_mode)
+/// and [`with_priority`](Self::with_priority) to get a client with different settings,
+/// for a single call or for good:
+///
+/// ```text
+/// let ticket = client.with_timeout(Duration::from_millis(50)).get(id)?;
+/// ```
+#[derive(Clone)]
+pub struct TicketStoreClient {
+    lanes: Lanes,
+    pub(crate) state: Arc<ServerState>,
+    timeout: Option<Duration>,
+    send_mode: SendMode,
+    priority: Priority,
+}
+
+/// What to do when the server's queue is full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SendMode {
+    /// Fail right away with [`ClientError::Overloaded`].
+    #[default]
+    FailFast,
+    /// Wait for the server to make room: backpressure.
+    Block,
+}
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ClientError {
+    #[error("The store is overloaded")]
+    Overloaded,
+    #[error("The store is shutting down")]
+    ShuttingDown,
+    #[error("The store is no longer reachable")]
+    Disconnected,
+    /// The command may still be executed by the server, later on.
+    #[error("The store didn't reply in time")]
+    TimedOut,
+    #[error("The store crashed")]
+    ServerPanicked,
+    /// Only over a socket: a message couldn't be decoded.
+    #[error("The store sent or received a malformed message")]
+    Malformed,
+}
+
+impl TicketStoreClient {
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+    pub(crate) fn new(lanes: Lanes, state: Arc<ServerState>) -> Self {
+        Self {
+            lanes,
+            state,
+            timeout: Some(Self::DEFAULT_TIMEOUT),
+            send_mode: SendMode::default(),
+            priority: Priority::default(),
+        }
+    }
+
+    /// A client that waits at most `timeout` for each reply.
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..self.clone()
+        }
+    }
+
+    /// A client that waits for replies for as long as it takes.
+    pub fn without_timeout(&self) -> Self {
+        Self {
+            timeout: None,
+            ..self.clone()
+        }
+    }
+
+    pub fn with_send_mode(&self, send_mode: SendMode) -> Self {
+        Self {
+            send_mode,
+            ..self.clone()
+        }
+    }
+
+    /// A client whose commands go through the queue for `priority`.
+    pub fn with_priority(&self, priority: Priority) -> Self {
+        Self {
+            priority,
+            ..self.clone()
+        }
+    }
+
+    pub fn insert(&self, draft: TicketDraft) -> Result<TicketId, ClientError> {
+        self.call(Insert(draft))
+    }
+
+    pub fn get(&self, id: TicketId) -> Result<Option<Ticket>, ClientError> {
+        self.read(Get(id))
+    }
+
+    /// Apply `patch`, returning the updated ticket.
+    /// `None` if there's no ticket with that id.
+    pub fn update(&self, patch: TicketPatch) -> Result<Option<Ticket>, ClientError> {
+        self.call(Update(patch))
+    }
+
+    /// Run all of `ops` in a single round trip, as one atomic step.
+    ///
+    /// The responses come back in the same order as `ops`.
+    pub fn batch(&self, ops: Vec<BatchOp>) -> Result<Vec<BatchResponse>, ClientError> {
+        self.call(Batch(ops))
+    }
+
+    pub fn insert_many(
+        &self,
+        drafts: impl IntoIterator<Item = TicketDraft>,
+    ) -> Result<Vec<TicketId>, ClientError> {
+        let ops = drafts.into_iter().map(BatchOp::Insert).collect();
+        let responses = self.batch(ops)?;
+        Ok(responses
+            .into_iter()
+            .map(|response| match response {
+                BatchResponse::Inserted(id) => id,
+                _ => unreachable!("An insert always yields an id"),
+            })
+            .collect())
+    }
+
+    pub fn get_many(
+        &self,
+        ids: impl IntoIterator<Item = TicketId>,
+    ) -> Result<Vec<Option<Ticket>>, ClientError> {
+        let ops = ids.into_iter().map(BatchOp::Get).collect();
+        let responses = self.batch(ops)?;
+        Ok(responses
+            .into_iter()
+            .map(|response| match response {
+                BatchResponse::Got(ticket) => ticket,
+                _ => unreachable!("A get always yields a ticket, if any"),
+            })
+            .collect())
+    }
+
+    /// Get pushed every change to a ticket that matches `filter`, from now on.
+    ///
+    /// Up to `capacity` events are queued for the subscriber: on top of that,
+    /// events are skipped rather than holding up the server.
+    /// A `capacity` of 0 is taken as 1: with no room at all, every event would be skipped.
+    pub fn subscribe(
+        &self,
+        filter: EventFilter,
+        capacity: usize,
+    ) -> Result<Subscription, ClientError> {
+        let (sender, receiver) = sync_channel(capacity.max(1));
+        let missed = Arc::new(AtomicU64::new(0));
+        self.call(Subscribe {
+            filter,
+            sender,
+            missed: missed.clone(),
+        })?;
+        Ok(Subscription { receiver, missed })
+    }
+
+    pub fn stats(&self) -> Result<Stats, ClientError> {
+        self.read(GetStats)
+    }
+
+    pub(crate) fn call<M: Message>(&self, message: M) -> Result<M::Reply, ClientError>
+    where
+        ServerData: Handler<M>,
+    {
+        let (command, pending) = Envelope::new(message);
+        self.request(command, pending)
+    }
+
+    /// Like [`call`](Self::call), for a command that can be served next to other reads.
+    fn read<M: Message>(&self, message: M) -> Result<M::Reply, ClientError>
+    where
+        ServerData: ReadHandler<M>,
+    {
+        let (command, pending) = Envelope::read(message);
+        self.request(command, pending)
+    }
+
+    fn request<R>(&self, command: Command, pending: Pending<R>) -> Result<R, ClientError> {
+        if self.state.shutting_down.load(Ordering::Acquire) {
+            return Err(ClientError::ShuttingDown);
+        }
+        self.send(command, self.send_mode)?;
+        let reply = match self.timeout {
+            Some(timeout) => pending.recv_timeout(timeout),
+            None => pending.recv(),
+        };
+        reply.map_err(|e| match e {
+            ActorError::TimedOut => ClientError::TimedOut,
+            ActorError::Panicked => ClientError::ServerPanicked,
+            ActorError::Full => ClientError::Overloaded,
+            ActorError::Stopped => self.disconnected(),
+        })
+    }
+
+    /// Queue `command` for the server, keeping the metrics up to date.
+    pub(crate) fn send(&self, command: Command, send_mode: SendMode) -> Result<(), ClientError> {
+        let metrics = &self.state.metrics;
+        // Accounted for beforehand: the server may pick the command up
+        // before `send` even returns.
+        metrics.accepted();
+        let outcome = match send_mode {
+            SendMode::FailFast => {
+                self.lanes
+                    .try_send(self.priority, command)
+                    .map_err(|e| match e {
+                        TrySendError::Full(_) => ClientError::Overloaded,
+                        TrySendError::Disconnected(_) => self.disconnected(),
+                    })
+            }
+            SendMode::Block => self
+                .lanes
+                .send(self.priority, command)
+                .map_err(|_| self.disconnected()),
+        };
+        if let Err(e) = &outcome {
+            metrics.rejected(*e == ClientError::Overloaded);
+        }
+        outcome
+    }
+
+    /// Queue `command` behind every other command, waiting for room if need be.
+    pub(crate) fn send_last(&self, command: Command) -> Result<(), ClientError> {
+        let metrics = &self.state.metrics;
+        metrics.accepted();
+        self.lanes.send_last(command).map_err(|_| {
+            metrics.rejected(false);
+            self.disconnected()
+        })
+    }
+
+    /// Why the server went away.
+    fn disconnected(&self) -> ClientError {
+        if self.state.panicked.load(Ordering::Acquire) {
+            ClientError::ServerPanicked
+        } else if self.state.shutting_down.load(Ordering::Acquire) {
+            // The command was queued behind the shutdown request and dropped.
+            ClientError::ShuttingDown
+        } else {
+            ClientError::Disconnected
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lanes::{lanes, Inbox};
+    use ticket_store::data::TicketId;
+
+    /// A client wired to a fake server that handles a single command with `serve`.
+    fn fake_server(
+        capacity: usize,
+        serve: impl FnOnce(Command, &ServerState) + Send + 'static,
+    ) -> (TicketStoreClient, std::thread::JoinHandle<Inbox>) {
+        let (lanes, mut inbox) = lanes(capacity, capacity, 1);
+        let state = Arc::new(ServerState::default());
+        let client = TicketStoreClient::new(lanes, state.clone());
+        let server = std::thread::spawn(move || {
+            let command = inbox.recv().unwrap();
+            serve(command, &state);
+            inbox
+        });
+        (client, server)
+    }
+
+    #[test]
+    fn times_out() {
+        let (client, _server) = fake_server(1, |command, _| {
+            std::thread::sleep(Duration::from_millis(200));
+            drop(command);
+        });
+        let client = client.with_timeout(Duration::from_millis(10));
+        assert_eq!(client.get(TicketId::from(0)), Err(ClientError::TimedOut));
+    }
+
+    #[test]
+    fn reports_a_crashed_server() {
+        let (client, server) = fake_server(1, |command, state| {
+            state.panicked.store(true, Ordering::Release);
+            drop(command);
+        });
+        assert_eq!(
+            client.get(TicketId::from(0)),
+            Err(ClientError::ServerPanicked)
+        );
+        drop(server.join());
+        assert_eq!(
+            client.get(TicketId::from(0)),
+            Err(ClientError::ServerPanicked)
+        );
+    }
+
+    #[test]
+    fn reports_a_disconnected_server() {
+        let (client, server) = fake_server(1, |command, _| drop(command));
+        assert_eq!(
+            client.without_timeout().get(TicketId::from(0)),
+            Err(ClientError::Disconnected)
+        );
+        drop(server.join());
+        assert_eq!(
+            client.get(TicketId::from(0)),
+            Err(ClientError::Disconnected)
+        );
+    }
+
+    #[test]
+    fn fail_fast_or_block_on_a_full_queue() {
+        let (lanes, mut inbox) = lanes(1, 1, 1);
+        let client = TicketStoreClient::new(lanes, Arc::new(ServerState::default()))
+            .with_timeout(Duration::from_millis(10));
+        // Queued, but nobody serves it.
+        assert_eq!(client.get(TicketId::from(0)), Err(ClientError::TimedOut));
+        assert_eq!(client.get(TicketId::from(0)), Err(ClientError::Overloaded));
+        // The other queue still has room.
+        let low = client.with_priority(Priority::Low);
+        assert_eq!(low.get(TicketId::from(0)), Err(ClientError::TimedOut));
+
+        let server = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            for _ in 0..3 {
+                let _command = inbox.recv().unwrap();
+            }
+        });
+        // Waits for the server to make room, then for the reply (that never comes).
+        let blocking = client.with_send_mode(SendMode::Block);
+        assert_eq!(
+            blocking.get(TicketId::from(0)),
+            Err(ClientError::Disconnected)
+        );
+        server.join().unwrap();
+    }
+}
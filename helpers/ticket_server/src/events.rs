@@ -0,0 +1,159 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, SyncSender, TryRecvError, TrySendError};
+use std::sync::Arc;
+use std::time::Duration;
+use ticket_store::data::{Status, Ticket, TicketId};
+
+/// A change to a ticket, pushed to the subscribers it matches.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TicketEvent {
+    pub id: TicketId,
+    pub kind: EventKind,
+    /// The ticket right after the change.
+    pub ticket: Ticket,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    Created,
+    Updated,
+}
+
+/// Which events a subscriber is interested in. `None` matches anything,
+/// so the default filter lets every event through.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EventFilter {
+    pub id: Option<TicketId>,
+    pub kind: Option<EventKind>,
+    /// Matches the status of the ticket after the change.
+    pub status: Option<Status>,
+}
+
+impl EventFilter {
+    pub fn matches(&self, event: &TicketEvent) -> bool {
+        self.id.is_none_or(|id| id == event.id)
+            && self.kind.is_none_or(|kind| kind == event.kind)
+            && self
+                .status
+                .is_none_or(|status| status == event.ticket.status)
+    }
+}
+
+/// The server's end of a subscription.
+pub(crate) struct Subscriber {
+    pub(crate) filter: EventFilter,
+    pub(crate) sender: SyncSender<TicketEvent>,
+    pub(crate) missed: Arc<AtomicU64>,
+}
+
+impl Subscriber {
+    /// Push `event` without ever blocking: if the subscriber's queue is full,
+    /// the event is skipped and counted as missed.
+    /// Returns `false` once the subscriber is gone.
+    pub(crate) fn notify(&self, event: &TicketEvent) -> bool {
+        if !self.filter.matches(event) {
+            return true;
+        }
+        match self.sender.try_send(event.clone()) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                self.missed.fetch_add(1, Ordering::Relaxed);
+                true
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        }
+    }
+}
+
+/// The client's end of a subscription. Dropping it unsubscribes.
+///
+/// The server never waits for a slow subscriber: when the queue is full,
+/// new events are skipped. [`missed`](Self::missed) tells how many were.
+pub struct Subscription {
+    pub(crate) receiver: Receiver<TicketEvent>,
+    pub(crate) missed: Arc<AtomicU64>,
+}
+
+impl Subscription {
+    /// Wait for the next event. Returns `None` once the server is gone.
+    pub fn recv(&self) -> Option<TicketEvent> {
+        self.receiver.recv().ok()
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<TicketEvent, RecvTimeoutError> {
+        self.receiver.recv_timeout(timeout)
+    }
+
+    pub fn try_recv(&self) -> Result<TicketEvent, TryRecvError> {
+        self.receiver.try_recv()
+    }
+
+    /// How many events were skipped because the queue was full.
+    pub fn missed(&self) -> u64 {
+        self.missed.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::sync_channel;
+    use std::time::SystemTime;
+    use ticket_fields::test_helpers::{ticket_description, ticket_title};
+
+    fn event(id: u64, kind: EventKind, status: Status) -> TicketEvent {
+        let id = TicketId::from(id);
+        TicketEvent {
+            id,
+            kind,
+            ticket: Ticket {
+                id,
+                title: ticket_title(),
+                description: ticket_description(),
+                status,
+                created_at: SystemTime::now(),
+                resolved_at: None,
+            },
+        }
+    }
+
+    #[test]
+    fn filters() {
+        let created = event(1, EventKind::Created, Status::ToDo);
+        let done = event(2, EventKind::Updated, Status::Done);
+
+        let everything = EventFilter::default();
+        assert!(everything.matches(&created) && everything.matches(&done));
+
+        let by_id = EventFilter {
+            id: Some(TicketId::from(1)),
+            ..EventFilter::default()
+        };
+        assert!(by_id.matches(&created) && !by_id.matches(&done));
+
+        let closed = EventFilter {
+            kind: Some(EventKind::Updated),
+            status: Some(Status::Done),
+            ..EventFilter::default()
+        };
+        assert!(!closed.matches(&created) && closed.matches(&done));
+    }
+
+    #[test]
+    fn slow_subscribers_miss_events() {
+        let (sender, receiver) = sync_channel(1);
+        let subscriber = Subscriber {
+            filter: EventFilter::default(),
+            sender,
+            missed: Arc::default(),
+        };
+        let event = event(1, EventKind::Created, Status::ToDo);
+
+        assert!(subscriber.notify(&event));
+        assert!(subscriber.notify(&event));
+        assert_eq!(subscriber.missed.load(Ordering::Relaxed), 1);
+
+        drop(receiver);
+        assert!(!subscriber.notify(&event));
+    }
+}
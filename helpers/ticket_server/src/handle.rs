@@ -0,0 +1,248 @@
+use crate::client::TicketStoreClient;
+use crate::lanes::lanes;
+use crate::pool;
+use crate::server::{server, ServerData, ServerState, Shutdown};
+use crate::supervisor::{supervise, RestartPolicy};
+use actor::Envelope;
+use std::any::Any;
+use std::panic::resume_unwind;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, RwLock};
+use std::thread::JoinHandle;
+
+/// Owns the server thread. Dropping it detaches the thread, which keeps
+/// running until every client is gone.
+pub struct ServerHandle {
+    client: TicketStoreClient,
+    thread: JoinHandle<()>,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("The ticket store server panicked: {message}")]
+pub struct ServerPanicked {
+    pub message: String,
+}
+
+impl ServerPanicked {
+    pub(crate) fn from_payload(payload: Box<dyn Any + Send>) -> Self {
+        let message = if let Some(message) = payload.downcast_ref::<&str>() {
+            message.to_string()
+        } else if let Some(message) = payload.downcast_ref::<String>() {
+            message.clone()
+        } else {
+            "<non-string panic payload>".to_string()
+        };
+        Self { message }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// How many high-priority commands can be queued before clients get `Overloaded`.
+    pub capacity: usize,
+    /// The same, for low-priority commands: see [`Priority`](crate::Priority).
+    pub low_priority_capacity: usize,
+    /// How many high-priority commands are served for each low-priority one,
+    /// while both are waiting. At least 1.
+    pub high_priority_weight: usize,
+    pub restart_policy: RestartPolicy,
+    /// How many threads serve reads in parallel, next to the one applying writes.
+    /// With `0`, a single thread serves everything.
+    pub workers: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            capacity: 16,
+            low_priority_capacity: 16,
+            high_priority_weight: 4,
+            restart_policy: RestartPolicy::default(),
+            workers: 0,
+        }
+    }
+}
+
+/// Start a server with room for `capacity` commands of each priority.
+pub fn launch(capacity: usize) -> ServerHandle {
+    launch_with(Config {
+        capacity,
+        low_priority_capacity: capacity,
+        ..Config::default()
+    })
+}
+
+/// Start a server whose reads are served by `workers` threads in parallel.
+pub fn launch_pool(capacity: usize, workers: usize) -> ServerHandle {
+    launch_with(Config {
+        capacity,
+        low_priority_capacity: capacity,
+        workers,
+        ..Config::default()
+    })
+}
+
+/// Start the server on its own thread.
+///
+/// If a command makes it panic, the server is restarted according to
+/// `config.restart_policy`, with the tickets and subscribers it had before that command.
+/// Clients keep working across restarts: they don't need to reconnect.
+///
+/// # Panics
+///
+/// Panics if `config.high_priority_weight` is 0.
+pub fn launch_with(config: Config) -> ServerHandle {
+    let (lanes, mut inbox) = lanes(
+        config.capacity,
+        config.low_priority_capacity,
+        config.high_priority_weight,
+    );
+    let state = Arc::new(ServerState::default());
+    let thread = {
+        let state = state.clone();
+        std::thread::spawn(move || {
+            let on_restart = || {
+                state.restarts.fetch_add(1, Ordering::Relaxed);
+            };
+            let outcome = if config.workers == 0 {
+                let mut data = ServerData::new(state.metrics.clone());
+                supervise(
+                    &config.restart_policy,
+                    &mut data,
+                    |data| server(&mut inbox, data),
+                    on_restart,
+                )
+            } else {
+                let mut data = RwLock::new(ServerData::new(state.metrics.clone()));
+                supervise(
+                    &config.restart_policy,
+                    &mut data,
+                    |data| pool::server(&mut inbox, data, config.workers),
+                    on_restart,
+                )
+            };
+            if let Err(payload) = outcome {
+                // Flag the crash before `inbox` goes away, so that clients
+                // whose commands are dropped with it know why.
+                state.panicked.store(true, Ordering::Release);
+                resume_unwind(payload);
+            }
+        })
+    };
+    let client = TicketStoreClient::new(lanes, state);
+    ServerHandle { client, thread }
+}
+
+impl ServerHandle {
+    pub fn client(&self) -> TicketStoreClient {
+        self.client.clone()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// How many times the server was restarted after a crash.
+    pub fn restarts(&self) -> usize {
+        self.client.state.restarts.load(Ordering::Relaxed)
+    }
+
+    /// Stop the server and wait for its thread to exit.
+    ///
+    /// New calls fail with [`ClientError::ShuttingDown`](crate::ClientError::ShuttingDown)
+    /// right away, while commands that were already queued are still served.
+    pub fn shutdown(self) -> Result<(), ServerPanicked> {
+        self.client
+            .state
+            .shutting_down
+            .store(true, Ordering::Release);
+        // Behind the in-flight commands of both priorities, so that they're all served first.
+        // It only fails if the server thread is already gone.
+        let _ = self.client.send_last(Envelope::cast(Shutdown));
+        drop(self.client);
+        self.thread.join().map_err(ServerPanicked::from_payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::Crash;
+    use crate::ClientError;
+    use std::time::Duration;
+    use ticket_store::test_helpers::ticket_draft;
+
+    fn crash(client: &TicketStoreClient) -> Result<(), ClientError> {
+        client.call(Crash)
+    }
+
+    #[test]
+    fn restarts_with_the_same_tickets() {
+        let server = launch_with(Config {
+            capacity: 4,
+            restart_policy: RestartPolicy {
+                backoff: Duration::from_millis(1),
+                ..RestartPolicy::default()
+            },
+            ..Config::default()
+        });
+        let client = server.client();
+        let id = client.insert(ticket_draft()).unwrap();
+
+        assert_eq!(crash(&client), Err(ClientError::ServerPanicked));
+        assert_eq!(client.get(id).unwrap().unwrap().id, id);
+        assert_ne!(client.insert(ticket_draft()).unwrap(), id);
+        assert_eq!(server.restarts(), 1);
+
+        server.shutdown().unwrap();
+    }
+
+    #[test]
+    fn restarts_a_pool_with_the_same_tickets() {
+        let server = launch_with(Config {
+            capacity: 4,
+            workers: 2,
+            restart_policy: RestartPolicy {
+                backoff: Duration::from_millis(1),
+                ..RestartPolicy::default()
+            },
+            ..Config::default()
+        });
+        let client = server.client();
+        let id = client.insert(ticket_draft()).unwrap();
+
+        assert_eq!(crash(&client), Err(ClientError::ServerPanicked));
+        assert_eq!(client.get(id).unwrap().unwrap().id, id);
+        assert_eq!(server.restarts(), 1);
+
+        server.shutdown().unwrap();
+    }
+
+    #[test]
+    fn gives_up_according_to_the_policy() {
+        let server = launch_with(Config {
+            capacity: 4,
+            restart_policy: RestartPolicy::never(),
+            ..Config::default()
+        });
+        let client = server.client();
+
+        assert_eq!(crash(&client), Err(ClientError::ServerPanicked));
+        assert_eq!(
+            client.insert(ticket_draft()),
+            Err(ClientError::ServerPanicked)
+        );
+        assert_eq!(server.shutdown().unwrap_err().message, "Crash requested");
+    }
+
+    #[test]
+    fn reports_panics() {
+        let (lanes, _inbox) = lanes(1, 1, 1);
+        let handle = ServerHandle {
+            client: TicketStoreClient::new(lanes, Arc::default()),
+            thread: std::thread::spawn(|| panic!("Oh no")),
+        };
+        let error = handle.shutdown().unwrap_err();
+        assert_eq!(error.message, "Oh no");
+    }
+}
@@ -0,0 +1,222 @@
+use crate::server::Command;
+use std::sync::mpsc::{
+    sync_channel, Receiver, RecvError, SendError, SyncSender, TryRecvError, TrySendError,
+};
+
+/// Which queue a client's commands go through.
+///
+/// Each priority has a bounded queue of its own: a flood of low-priority
+/// commands can fill theirs up, but it doesn't take room from high-priority ones.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Priority {
+    /// Interactive traffic, e.g. a UI waiting on a `get`.
+    #[default]
+    High,
+    /// Bulk traffic, e.g. an import.
+    Low,
+}
+
+/// The clients' end of the queues.
+///
+/// Every command is followed by a ring of the doorbell, which is what the
+/// server waits on: it can't block on several queues at once.
+pub(crate) struct Lanes<T = Command> {
+    high: SyncSender<T>,
+    low: SyncSender<T>,
+    /// Served once both lanes are empty, see [`Lanes::send_last`].
+    last: SyncSender<T>,
+    doorbell: SyncSender<()>,
+}
+
+impl<T> Clone for Lanes<T> {
+    fn clone(&self) -> Self {
+        Self {
+            high: self.high.clone(),
+            low: self.low.clone(),
+            last: self.last.clone(),
+            doorbell: self.doorbell.clone(),
+        }
+    }
+}
+
+/// The server's end of the queues.
+pub(crate) struct Inbox<T = Command> {
+    high: Receiver<T>,
+    low: Receiver<T>,
+    last: Receiver<T>,
+    doorbell: Receiver<()>,
+    high_priority_weight: usize,
+    /// How many high-priority commands were served in a row.
+    streak: usize,
+}
+
+/// Bounded queues with room for `high_capacity` and `low_capacity` commands.
+///
+/// While both have commands waiting, the server serves `high_priority_weight`
+/// high-priority commands for each low-priority one.
+///
+/// # Panics
+///
+/// Panics if `high_priority_weight` is 0.
+pub(crate) fn lanes<T>(
+    high_capacity: usize,
+    low_capacity: usize,
+    high_priority_weight: usize,
+) -> (Lanes<T>, Inbox<T>) {
+    assert!(
+        high_priority_weight > 0,
+        "High-priority commands need a weight of at least 1"
+    );
+    // The server never blocks on the queues themselves, so they need room
+    // for at least one command.
+    let (high_sender, high_receiver) = sync_channel(high_capacity.max(1));
+    let (low_sender, low_receiver) = sync_channel(low_capacity.max(1));
+    let (last_sender, last_receiver) = sync_channel(1);
+    // There are never more rings pending than commands queued:
+    // ringing never blocks.
+    let (doorbell_sender, doorbell_receiver) =
+        sync_channel(high_capacity.max(1) + low_capacity.max(1) + 1);
+    let lanes = Lanes {
+        high: high_sender,
+        low: low_sender,
+        last: last_sender,
+        doorbell: doorbell_sender,
+    };
+    let inbox = Inbox {
+        high: high_receiver,
+        low: low_receiver,
+        last: last_receiver,
+        doorbell: doorbell_receiver,
+        high_priority_weight,
+        streak: 0,
+    };
+    (lanes, inbox)
+}
+
+impl<T> Lanes<T> {
+    pub(crate) fn try_send(&self, priority: Priority, command: T) -> Result<(), TrySendError<T>> {
+        self.lane(priority).try_send(command)?;
+        self.ring();
+        Ok(())
+    }
+
+    pub(crate) fn send(&self, priority: Priority, command: T) -> Result<(), SendError<T>> {
+        self.lane(priority).send(command)?;
+        self.ring();
+        Ok(())
+    }
+
+    /// Queue `command` behind everything, whatever its priority: it's served
+    /// once both lanes are empty. Waits for room, as [`send`](Lanes::send) does.
+    pub(crate) fn send_last(&self, command: T) -> Result<(), SendError<T>> {
+        self.last.send(command)?;
+        self.ring();
+        Ok(())
+    }
+
+    fn lane(&self, priority: Priority) -> &SyncSender<T> {
+        match priority {
+            Priority::High => &self.high,
+            Priority::Low => &self.low,
+        }
+    }
+
+    fn ring(&self) {
+        // It only fails if the server is gone, in which case
+        // the command is dropped along with the queue anyway.
+        let _ = self.doorbell.send(());
+    }
+}
+
+impl<T> Inbox<T> {
+    /// Wait for the next command to serve. Fails once every client is gone.
+    pub(crate) fn recv(&mut self) -> Result<T, RecvError> {
+        loop {
+            self.doorbell.recv()?;
+            let low_first = self.streak >= self.high_priority_weight;
+            let command = if low_first {
+                self.take(Priority::Low)
+                    .or_else(|_| self.take(Priority::High))
+            } else {
+                self.take(Priority::High)
+                    .or_else(|_| self.take(Priority::Low))
+            }
+            .or_else(|_| self.last.try_recv());
+            // Each ring follows a command, so there's always one to take.
+            if let Ok(command) = command {
+                return Ok(command);
+            }
+        }
+    }
+
+    fn take(&mut self, priority: Priority) -> Result<T, TryRecvError> {
+        let command = match priority {
+            Priority::High => self.high.try_recv()?,
+            Priority::Low => self.low.try_recv()?,
+        };
+        match priority {
+            Priority::High => self.streak += 1,
+            Priority::Low => self.streak = 0,
+        }
+        Ok(command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serves_both_lanes_with_weighted_fairness() {
+        let (lanes, mut inbox) = lanes::<u64>(8, 8, 2);
+        for i in 0..4 {
+            lanes.try_send(Priority::Low, 100 + i).unwrap();
+        }
+        for i in 0..6 {
+            lanes.try_send(Priority::High, i).unwrap();
+        }
+
+        let served: Vec<_> = (0..10).map(|_| inbox.recv().unwrap()).collect();
+        assert_eq!(served, [0, 1, 100, 2, 3, 101, 4, 5, 102, 103]);
+    }
+
+    #[test]
+    fn serves_the_last_command_once_both_lanes_are_empty() {
+        let (lanes, mut inbox) = lanes::<u64>(4, 4, 1);
+        lanes.try_send(Priority::Low, 100).unwrap();
+        lanes.try_send(Priority::Low, 101).unwrap();
+        lanes.try_send(Priority::High, 0).unwrap();
+        lanes.send_last(42).unwrap();
+        lanes.try_send(Priority::High, 1).unwrap();
+
+        let served: Vec<_> = (0..5).map(|_| inbox.recv().unwrap()).collect();
+        assert_eq!(served, [0, 100, 1, 101, 42]);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least 1")]
+    fn rejects_a_weight_of_zero() {
+        lanes::<u64>(1, 1, 0);
+    }
+
+    #[test]
+    fn a_full_low_lane_leaves_room_for_high_priority() {
+        let (lanes, _inbox) = lanes::<u64>(1, 2, 1);
+        lanes.try_send(Priority::Low, 0).unwrap();
+        lanes.try_send(Priority::Low, 1).unwrap();
+        assert!(matches!(
+            lanes.try_send(Priority::Low, 2),
+            Err(TrySendError::Full(_))
+        ));
+        lanes.try_send(Priority::High, 3).unwrap();
+    }
+
+    #[test]
+    fn fails_once_every_client_is_gone() {
+        let (lanes, mut inbox) = lanes::<u64>(1, 1, 1);
+        lanes.try_send(Priority::Low, 0).unwrap();
+        drop(lanes);
+        assert_eq!(inbox.recv().unwrap(), 0);
+        assert!(inbox.recv().is_err());
+    }
+}
@@ -0,0 +1,28 @@
+//! The channel-based ticket store server from `07_threads`, with the missing
+//! operational pieces: an explicit handle to stop it and collect its outcome,
+//! typed errors and timeouts for clients, automatic restarts after a crash,
+//! batches, change notifications, parallel reads, metrics and priorities.
+//! The server itself is an actor: see the `actor` helper crate.
+//! On Unix, the store can also be shared with other processes: see [`socket`].
+mod client;
+mod events;
+mod handle;
+mod lanes;
+mod metrics;
+mod pool;
+mod server;
+#[cfg(test)]
+mod sim;
+#[cfg(unix)]
+pub mod socket;
+mod supervisor;
+mod wire;
+
+pub use client::{ClientError, SendMode, TicketStoreClient};
+pub use events::{EventFilter, EventKind, Subscription, TicketEvent};
+pub use handle::{launch, launch_pool, launch_with, Config, ServerHandle, ServerPanicked};
+pub use lanes::Priority;
+pub use metrics::{CommandKind, CommandStats, LatencyHistogram, Stats};
+pub use server::{BatchOp, BatchResponse};
+pub use supervisor::RestartPolicy;
+pub use ticket_store::data;
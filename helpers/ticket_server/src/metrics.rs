@@ -0,0 +1,270 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Which command was handled, as reported in [`Stats`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CommandKind {
+    Insert,
+    Get,
+    Update,
+    Batch,
+    Subscribe,
+    Stats,
+    Shutdown,
+}
+
+impl CommandKind {
+    pub const ALL: &'static [CommandKind] = &[
+        CommandKind::Insert,
+        CommandKind::Get,
+        CommandKind::Update,
+        CommandKind::Batch,
+        CommandKind::Subscribe,
+        CommandKind::Stats,
+        CommandKind::Shutdown,
+    ];
+}
+
+/// Upper bounds of the latency buckets. Anything slower lands in a last,
+/// unbounded bucket.
+const BUCKETS: [Duration; 7] = [
+    Duration::from_micros(1),
+    Duration::from_micros(10),
+    Duration::from_micros(100),
+    Duration::from_millis(1),
+    Duration::from_millis(10),
+    Duration::from_millis(100),
+    Duration::from_secs(1),
+];
+
+/// Counters updated by clients and the server alike, without locking.
+#[derive(Default)]
+pub(crate) struct Metrics {
+    commands: [CommandMetrics; CommandKind::ALL.len()],
+    accepted: AtomicU64,
+    overloaded: AtomicU64,
+    backlog: AtomicUsize,
+}
+
+#[derive(Default)]
+struct CommandMetrics {
+    handled: AtomicU64,
+    panicked: AtomicU64,
+    latency: [AtomicU64; BUCKETS.len() + 1],
+    total_latency_nanos: AtomicU64,
+}
+
+impl Metrics {
+    /// A command is about to be queued for the server.
+    /// If that fails, it's taken back with [`rejected`](Self::rejected).
+    pub(crate) fn accepted(&self) {
+        self.accepted.fetch_add(1, Ordering::Relaxed);
+        self.backlog.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Take back a command that couldn't be queued after all.
+    pub(crate) fn rejected(&self, overloaded: bool) {
+        self.accepted.fetch_sub(1, Ordering::Relaxed);
+        self.backlog.fetch_sub(1, Ordering::Relaxed);
+        if overloaded {
+            self.overloaded.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Handle a command of the given kind with `f`, within a `tracing` span,
+    /// recording how long it took or that it panicked.
+    pub(crate) fn observe<R>(&self, kind: CommandKind, f: impl FnOnce() -> R) -> R {
+        self.backlog.fetch_sub(1, Ordering::Relaxed);
+        let _span = tracing::debug_span!("command", ?kind).entered();
+        let _timer = Timer {
+            metrics: &self.commands[kind as usize],
+            started: Instant::now(),
+        };
+        f()
+    }
+
+    pub(crate) fn snapshot(&self) -> Stats {
+        let commands = CommandKind::ALL
+            .iter()
+            .map(|kind| {
+                let metrics = &self.commands[*kind as usize];
+                let latency = LatencyHistogram {
+                    buckets: BUCKETS
+                        .iter()
+                        .copied()
+                        .chain([Duration::MAX])
+                        .zip(&metrics.latency)
+                        .map(|(bound, count)| (bound, count.load(Ordering::Relaxed)))
+                        .collect(),
+                    total: Duration::from_nanos(
+                        metrics.total_latency_nanos.load(Ordering::Relaxed),
+                    ),
+                };
+                let stats = CommandStats {
+                    handled: metrics.handled.load(Ordering::Relaxed),
+                    panicked: metrics.panicked.load(Ordering::Relaxed),
+                    latency,
+                };
+                (*kind, stats)
+            })
+            .collect();
+        Stats {
+            commands,
+            accepted: self.accepted.load(Ordering::Relaxed),
+            overloaded: self.overloaded.load(Ordering::Relaxed),
+            backlog: self.backlog.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Records the outcome of a command when dropped, even if it panicked.
+struct Timer<'a> {
+    metrics: &'a CommandMetrics,
+    started: Instant,
+}
+
+impl Drop for Timer<'_> {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            self.metrics.panicked.fetch_add(1, Ordering::Relaxed);
+            tracing::error!("The command panicked");
+            return;
+        }
+        let elapsed = self.started.elapsed();
+        let bucket = BUCKETS
+            .iter()
+            .position(|bound| elapsed <= *bound)
+            .unwrap_or(BUCKETS.len());
+        self.metrics.latency[bucket].fetch_add(1, Ordering::Relaxed);
+        self.metrics.total_latency_nanos.fetch_add(
+            u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX),
+            Ordering::Relaxed,
+        );
+        self.metrics.handled.fetch_add(1, Ordering::Relaxed);
+        tracing::debug!(?elapsed, "Command handled");
+    }
+}
+
+/// A snapshot of the server's metrics, since it was launched.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stats {
+    pub commands: BTreeMap<CommandKind, CommandStats>,
+    /// Commands that made it into the queue.
+    pub accepted: u64,
+    /// Commands turned down with [`ClientError::Overloaded`](crate::ClientError::Overloaded).
+    pub overloaded: u64,
+    /// Commands queued but not picked up yet.
+    pub backlog: usize,
+}
+
+impl Stats {
+    /// The share of commands that were turned down because the queue was full.
+    pub fn rejection_rate(&self) -> f64 {
+        let attempts = self.accepted + self.overloaded;
+        if attempts == 0 {
+            0.0
+        } else {
+            self.overloaded as f64 / attempts as f64
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommandStats {
+    pub handled: u64,
+    pub panicked: u64,
+    pub latency: LatencyHistogram,
+}
+
+/// How long the handled commands took.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LatencyHistogram {
+    /// The inclusive upper bound of each bucket, with how many commands fell into it.
+    /// The last bound is `Duration::MAX`.
+    pub buckets: Vec<(Duration, u64)>,
+    pub total: Duration,
+}
+
+impl LatencyHistogram {
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().map(|(_, count)| count).sum()
+    }
+
+    pub fn mean(&self) -> Option<Duration> {
+        let count = u32::try_from(self.count())
+            .ok()
+            .filter(|count| *count > 0)?;
+        Some(self.total / count)
+    }
+
+    /// The upper bound of the bucket holding the `q`-th quantile, e.g. `0.99`.
+    pub fn quantile(&self, q: f64) -> Option<Duration> {
+        let count = self.count();
+        if count == 0 {
+            return None;
+        }
+        let rank = (q.clamp(0.0, 1.0) * count as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        self.buckets.iter().find_map(|(bound, bucket)| {
+            seen += bucket;
+            (seen >= rank).then_some(*bound)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_commands() {
+        let metrics = Metrics::default();
+        for _ in 0..4 {
+            metrics.accepted();
+        }
+        metrics.rejected(true);
+        metrics.observe(CommandKind::Get, || {});
+        metrics.observe(CommandKind::Get, || {});
+        let panicked = std::panic::catch_unwind(|| {
+            metrics.observe(CommandKind::Insert, || panic!("Oops"));
+        });
+        assert!(panicked.is_err());
+
+        let stats = metrics.snapshot();
+        assert_eq!(stats.accepted, 3);
+        assert_eq!(stats.overloaded, 1);
+        assert_eq!(stats.rejection_rate(), 0.25);
+        assert_eq!(stats.backlog, 0);
+
+        let gets = &stats.commands[&CommandKind::Get];
+        assert_eq!((gets.handled, gets.panicked), (2, 0));
+        assert_eq!(gets.latency.count(), 2);
+        let inserts = &stats.commands[&CommandKind::Insert];
+        assert_eq!((inserts.handled, inserts.panicked), (0, 1));
+        assert_eq!(inserts.latency.count(), 0);
+    }
+
+    #[test]
+    fn quantiles() {
+        let histogram = LatencyHistogram {
+            buckets: vec![
+                (Duration::from_millis(1), 90),
+                (Duration::from_millis(10), 9),
+                (Duration::MAX, 1),
+            ],
+            total: Duration::from_millis(200),
+        };
+        assert_eq!(histogram.quantile(0.5), Some(Duration::from_millis(1)));
+        assert_eq!(histogram.quantile(0.99), Some(Duration::from_millis(10)));
+        assert_eq!(histogram.quantile(1.0), Some(Duration::MAX));
+        assert_eq!(histogram.mean(), Some(Duration::from_millis(2)));
+
+        let empty = LatencyHistogram {
+            buckets: vec![(Duration::MAX, 0)],
+            total: Duration::ZERO,
+        };
+        assert_eq!(empty.quantile(0.5), None);
+        assert_eq!(empty.mean(), None);
+    }
+}
@@ -0,0 +1,210 @@
+use crate::lanes::Inbox;
+use crate::server::{Command, ServerData};
+use actor::{Actor, Context};
+use std::any::Any;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::sync::mpsc::{sync_channel, Receiver, SendError};
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError, RwLock};
+
+/// Like [`server`](crate::server::server), with reads served by `workers`
+/// threads in parallel.
+///
+/// This thread dispatches commands in the order they come in. Reads are handed
+/// over to the workers, which share the store through an `RwLock`; writes are
+/// applied right here, one at a time, once every read handed over before them
+/// is served. A read always sees the writes that were received before it,
+/// and never those received after it.
+///
+/// A read that panics crashes the server too, as soon as the command being
+/// dispatched is done with. The actor's hooks are called as [`actor::run`] would.
+pub(crate) fn server(inbox: &mut Inbox, data: &RwLock<ServerData>, workers: usize) {
+    let mut ctx = Context::default();
+    write(data).started(&mut ctx);
+    let outcome = catch_unwind(AssertUnwindSafe(|| {
+        dispatch(inbox, data, workers, &mut ctx)
+    }));
+    write(data).stopped();
+    if let Err(payload) = outcome {
+        resume_unwind(payload);
+    }
+}
+
+/// The reads handed over to the workers and not served yet.
+#[derive(Default)]
+struct InFlight {
+    state: Mutex<Reads>,
+    /// Signalled whenever a read is served.
+    served: Condvar,
+}
+
+#[derive(Default)]
+struct Reads {
+    pending: usize,
+    /// The payload of the first read that panicked.
+    panic: Option<Box<dyn Any + Send>>,
+}
+
+impl InFlight {
+    fn lock(&self) -> MutexGuard<'_, Reads> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Wait until every read handed over so far is served.
+    fn drain(&self) {
+        let mut reads = self.lock();
+        while reads.pending > 0 {
+            reads = self
+                .served
+                .wait(reads)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+}
+
+fn dispatch(inbox: &mut Inbox, data: &RwLock<ServerData>, workers: usize, ctx: &mut Context) {
+    let (reads, queue) = sync_channel(workers);
+    let queue = Mutex::new(queue);
+    let in_flight = InFlight::default();
+    std::thread::scope(|scope| {
+        // Owned by the closure, so that the workers are told to stop
+        // even if a write makes it panic.
+        let reads = reads;
+        for _ in 0..workers {
+            scope.spawn(|| worker(&queue, data, &in_flight));
+        }
+        while !ctx.is_stopping() {
+            if let Some(payload) = in_flight.lock().panic.take() {
+                resume_unwind(payload);
+            }
+            let Ok(command) = inbox.recv() else {
+                break;
+            };
+            if command.is_read() {
+                in_flight.lock().pending += 1;
+                if let Err(SendError(command)) = reads.send(command) {
+                    in_flight.lock().pending -= 1;
+                    // Every worker is gone: serve it here instead.
+                    command.handle_read(&read(data));
+                }
+            } else {
+                // Otherwise a read that's still queued would see this write.
+                in_flight.drain();
+                command.handle(&mut write(data), ctx);
+            }
+        }
+    });
+}
+
+fn worker(queue: &Mutex<Receiver<Command>>, data: &RwLock<ServerData>, in_flight: &InFlight) {
+    loop {
+        // The queue is only locked while waiting for a command, not while serving it.
+        let command = queue.lock().unwrap_or_else(PoisonError::into_inner).recv();
+        let Ok(command) = command else {
+            break;
+        };
+        // The worker carries on: the dispatcher takes the panic over.
+        let outcome = catch_unwind(AssertUnwindSafe(|| command.handle_read(&read(data))));
+        let mut reads = in_flight.lock();
+        reads.pending -= 1;
+        if let Err(payload) = outcome {
+            reads.panic.get_or_insert(payload);
+        }
+        in_flight.served.notify_all();
+    }
+}
+
+// A write that panicked poisons the lock, but handlers leave the store
+// consistent when they panic: carry on with it.
+fn read(data: &RwLock<ServerData>) -> std::sync::RwLockReadGuard<'_, ServerData> {
+    data.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn write(data: &RwLock<ServerData>) -> std::sync::RwLockWriteGuard<'_, ServerData> {
+    data.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::client::SendMode;
+    use crate::data::{Status, TicketPatch};
+    use crate::handle::{launch_pool, launch_with, Config};
+    use crate::server::{Crash, Get, Sleep};
+    use crate::supervisor::RestartPolicy;
+    use crate::ClientError;
+    use actor::Envelope;
+    use std::time::Duration;
+    use ticket_store::test_helpers::ticket_draft;
+
+    #[test]
+    fn writes_wait_for_earlier_reads() {
+        let server = launch_pool(4, 1);
+        let client = server.client();
+        let id = client.insert(ticket_draft()).unwrap();
+
+        // Keeps the only worker busy, so that the `Get` stays queued.
+        let (sleep, _) = Envelope::read(Sleep(Duration::from_millis(50)));
+        client.send(sleep, SendMode::Block).unwrap();
+        let (get, pending) = Envelope::read(Get(id));
+        client.send(get, SendMode::Block).unwrap();
+        client
+            .update(TicketPatch {
+                id,
+                title: None,
+                description: None,
+                status: Some(Status::Done),
+            })
+            .unwrap();
+
+        assert_eq!(pending.recv().unwrap().unwrap().status, Status::ToDo);
+        server.shutdown().unwrap();
+    }
+
+    #[test]
+    fn a_panicking_read_crashes_the_server() {
+        let server = launch_with(Config {
+            capacity: 4,
+            workers: 2,
+            restart_policy: RestartPolicy {
+                backoff: Duration::from_millis(1),
+                ..RestartPolicy::default()
+            },
+            ..Config::default()
+        });
+        let client = server.client();
+        let id = client.insert(ticket_draft()).unwrap();
+
+        let (crash, pending) = Envelope::read(Crash);
+        client.send(crash, SendMode::Block).unwrap();
+        assert_eq!(pending.recv(), Err(actor::ActorError::Panicked));
+        // Each command gives the dispatcher a chance to notice.
+        while server.restarts() == 0 {
+            assert_eq!(client.get(id).unwrap().unwrap().id, id);
+        }
+        assert_eq!(server.restarts(), 1);
+
+        server.shutdown().unwrap();
+    }
+
+    #[test]
+    fn gives_up_on_a_panicking_read_according_to_the_policy() {
+        let server = launch_with(Config {
+            capacity: 4,
+            workers: 2,
+            restart_policy: RestartPolicy::never(),
+            ..Config::default()
+        });
+        let client = server.client();
+
+        let (crash, pending) = Envelope::read(Crash);
+        client.send(crash, SendMode::Block).unwrap();
+        assert!(pending.recv().is_err());
+        // Served until the dispatcher notices, turned down after.
+        let error = loop {
+            if let Err(e) = client.insert(ticket_draft()) {
+                break e;
+            }
+        };
+        assert_eq!(error, ClientError::ServerPanicked);
+        assert_eq!(server.shutdown().unwrap_err().message, "Crash requested");
+    }
+}
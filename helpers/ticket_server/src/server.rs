@@ -0,0 +1,370 @@
+use crate::events::{EventFilter, EventKind, Subscriber, TicketEvent};
+use crate::lanes::Inbox;
+use crate::metrics::{CommandKind, Metrics, Stats};
+use actor::{Actor, Context, Envelope, Handler, Message, ReadHandler};
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize};
+use std::sync::mpsc::SyncSender;
+use std::sync::Arc;
+use ticket_store::data::{Ticket, TicketDraft, TicketId, TicketPatch};
+use ticket_store::TicketStore;
+
+/// A command on its way to the server, with where to send the outcome.
+///
+/// Built with [`Envelope::read`] for the commands that leave the store alone,
+/// so that they can be served next to other reads.
+pub(crate) type Command = Envelope<ServerData>;
+
+pub(crate) struct Insert(pub(crate) TicketDraft);
+
+pub(crate) struct Get(pub(crate) TicketId);
+
+pub(crate) struct Update(pub(crate) TicketPatch);
+
+/// Applied in one go: no other command runs in between, and a panic
+/// halfway through rolls back the operations that were already applied.
+pub(crate) struct Batch(pub(crate) Vec<BatchOp>);
+
+/// Replies once the subscriber is registered: it gets every matching
+/// event from then on, unless its queue is full.
+pub(crate) struct Subscribe {
+    pub(crate) filter: EventFilter,
+    pub(crate) sender: SyncSender<TicketEvent>,
+    pub(crate) missed: Arc<AtomicU64>,
+}
+
+pub(crate) struct GetStats;
+
+/// Sent by [`ServerHandle::shutdown`](crate::ServerHandle::shutdown).
+/// Everything queued before it is still served.
+pub(crate) struct Shutdown;
+
+#[cfg(test)]
+pub(crate) struct Crash;
+
+/// A read that takes `0` to serve.
+#[cfg(test)]
+pub(crate) struct Sleep(pub(crate) std::time::Duration);
+
+impl Message for Insert {
+    type Reply = TicketId;
+}
+
+impl Message for Get {
+    type Reply = Option<Ticket>;
+}
+
+impl Message for Update {
+    type Reply = Option<Ticket>;
+}
+
+impl Message for Batch {
+    type Reply = Vec<BatchResponse>;
+}
+
+impl Message for Subscribe {
+    type Reply = ();
+}
+
+impl Message for GetStats {
+    type Reply = Stats;
+}
+
+impl Message for Shutdown {
+    type Reply = ();
+}
+
+#[cfg(test)]
+impl Message for Crash {
+    type Reply = ();
+}
+
+#[cfg(test)]
+impl Message for Sleep {
+    type Reply = ();
+}
+
+/// One operation of a batch. See [`TicketStoreClient::batch`](crate::TicketStoreClient::batch).
+#[derive(Clone, Debug, PartialEq)]
+pub enum BatchOp {
+    Insert(TicketDraft),
+    Get(TicketId),
+    Update(TicketPatch),
+}
+
+/// The outcome of a [`BatchOp`], at the same position in the response.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BatchResponse {
+    Inserted(TicketId),
+    Got(Option<Ticket>),
+    /// The updated ticket, or `None` if there's no ticket with that id.
+    Updated(Option<Ticket>),
+}
+
+/// What clients need to know about the server, beyond the channel itself.
+#[derive(Default)]
+pub(crate) struct ServerState {
+    pub(crate) shutting_down: AtomicBool,
+    /// Set once the server crashed and won't be restarted.
+    pub(crate) panicked: AtomicBool,
+    pub(crate) restarts: AtomicUsize,
+    pub(crate) metrics: Arc<Metrics>,
+}
+
+/// Everything the server keeps across restarts.
+pub(crate) struct ServerData {
+    store: TicketStore,
+    subscribers: Vec<Subscriber>,
+    metrics: Arc<Metrics>,
+}
+
+impl ServerData {
+    pub(crate) fn new(metrics: Arc<Metrics>) -> Self {
+        Self {
+            store: TicketStore::new(),
+            subscribers: Vec::new(),
+            metrics,
+        }
+    }
+
+    /// Tell the subscribers about a change to ticket `id`.
+    /// The ones that went away are forgotten along the way.
+    fn notify(&mut self, kind: EventKind, id: TicketId) {
+        if self.subscribers.is_empty() {
+            return;
+        }
+        let Some(ticket) = self.store.get(id) else {
+            return;
+        };
+        let event = TicketEvent {
+            id,
+            kind,
+            ticket: ticket.clone(),
+        };
+        self.subscribers
+            .retain(|subscriber| subscriber.notify(&event));
+    }
+
+    /// Run a handler, keeping the metrics up to date.
+    fn observe<R>(&mut self, kind: CommandKind, f: impl FnOnce(&mut Self) -> R) -> R {
+        let metrics = Arc::clone(&self.metrics);
+        metrics.observe(kind, || f(self))
+    }
+}
+
+/// Also started again after every restart, with the tickets it had before.
+impl Actor for ServerData {
+    fn started(&mut self, _ctx: &mut Context) {
+        tracing::debug!(tickets = self.store.len(), "Server started");
+    }
+
+    fn stopped(&mut self) {
+        tracing::debug!(tickets = self.store.len(), "Server stopped");
+    }
+}
+
+// Handlers only touch the store in their very last step, once nothing else can
+// fail: a panicking handler leaves the store as it was before the command.
+// Subscribers are notified once the change is in.
+
+impl Handler<Insert> for ServerData {
+    fn handle(&mut self, Insert(draft): Insert, _ctx: &mut Context) -> TicketId {
+        self.observe(CommandKind::Insert, |data| {
+            let id = data.store.add_ticket(draft);
+            data.notify(EventKind::Created, id);
+            id
+        })
+    }
+}
+
+impl ReadHandler<Get> for ServerData {
+    fn handle_read(&self, Get(id): Get) -> Option<Ticket> {
+        self.metrics
+            .observe(CommandKind::Get, || self.store.get(id).cloned())
+    }
+}
+
+impl Handler<Update> for ServerData {
+    fn handle(&mut self, Update(patch): Update, _ctx: &mut Context) -> Option<Ticket> {
+        self.observe(CommandKind::Update, |data| {
+            let id = patch.id;
+            update(&mut data.store, patch)?;
+            data.notify(EventKind::Updated, id);
+            data.store.get(id).cloned()
+        })
+    }
+}
+
+impl Handler<Batch> for ServerData {
+    fn handle(&mut self, Batch(ops): Batch, _ctx: &mut Context) -> Vec<BatchResponse> {
+        self.observe(CommandKind::Batch, |data| {
+            let (responses, changes) = apply_batch(&mut data.store, ops);
+            for change in changes {
+                match change {
+                    Change::Created(id) => data.notify(EventKind::Created, id),
+                    Change::Updated(previous) => data.notify(EventKind::Updated, previous.id),
+                }
+            }
+            responses
+        })
+    }
+}
+
+impl Handler<Subscribe> for ServerData {
+    fn handle(&mut self, subscribe: Subscribe, _ctx: &mut Context) {
+        self.observe(CommandKind::Subscribe, |data| {
+            let Subscribe {
+                filter,
+                sender,
+                missed,
+            } = subscribe;
+            data.subscribers.push(Subscriber {
+                filter,
+                sender,
+                missed,
+            });
+        })
+    }
+}
+
+impl ReadHandler<GetStats> for ServerData {
+    fn handle_read(&self, _: GetStats) -> Stats {
+        self.metrics
+            .observe(CommandKind::Stats, || self.metrics.snapshot())
+    }
+}
+
+impl Handler<Shutdown> for ServerData {
+    fn handle(&mut self, _: Shutdown, ctx: &mut Context) {
+        // Commands that were queued after the shutdown request are dropped
+        // together with the receiver: their callers get an error.
+        self.observe(CommandKind::Shutdown, |_| ctx.stop());
+    }
+}
+
+#[cfg(test)]
+impl Handler<Crash> for ServerData {
+    fn handle(&mut self, _: Crash, _ctx: &mut Context) {
+        // Not something clients can ask for: it's left out of the stats.
+        panic!("Crash requested");
+    }
+}
+
+#[cfg(test)]
+impl ReadHandler<Crash> for ServerData {
+    fn handle_read(&self, _: Crash) {
+        panic!("Crash requested");
+    }
+}
+
+#[cfg(test)]
+impl ReadHandler<Sleep> for ServerData {
+    fn handle_read(&self, Sleep(duration): Sleep) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// Serve commands until a shutdown is requested or every client is gone.
+pub(crate) fn server(inbox: &mut Inbox, data: &mut ServerData) {
+    // When there are no more senders, `recv` fails and the server shuts down.
+    actor::run(data, || inbox.recv().ok());
+}
+
+/// Returns the ticket as it was before the patch, if it exists.
+fn update(store: &mut TicketStore, patch: TicketPatch) -> Option<Ticket> {
+    let ticket = store.get_mut(patch.id)?;
+    let mut updated = ticket.clone();
+    updated.apply(patch);
+    Some(std::mem::replace(ticket, updated))
+}
+
+/// What a batch changed, in order: enough to undo it.
+enum Change {
+    Created(TicketId),
+    /// Holds the ticket as it was before the update.
+    Updated(Ticket),
+}
+
+fn apply_batch(store: &mut TicketStore, ops: Vec<BatchOp>) -> (Vec<BatchResponse>, Vec<Change>) {
+    atomically(store, |store, changes| {
+        ops.into_iter()
+            .map(|op| apply(store, op, changes))
+            .collect()
+    })
+}
+
+fn apply(store: &mut TicketStore, op: BatchOp, changes: &mut Vec<Change>) -> BatchResponse {
+    match op {
+        BatchOp::Insert(draft) => {
+            let id = store.add_ticket(draft);
+            changes.push(Change::Created(id));
+            BatchResponse::Inserted(id)
+        }
+        BatchOp::Get(id) => BatchResponse::Got(store.get(id).cloned()),
+        BatchOp::Update(patch) => {
+            let id = patch.id;
+            if let Some(previous) = update(store, patch) {
+                changes.push(Change::Updated(previous));
+            }
+            BatchResponse::Updated(store.get(id).cloned())
+        }
+    }
+}
+
+/// Run `f`, which records what it changes: if it panics, the changes are
+/// undone before the panic carries on.
+fn atomically<R>(
+    store: &mut TicketStore,
+    f: impl FnOnce(&mut TicketStore, &mut Vec<Change>) -> R,
+) -> (R, Vec<Change>) {
+    let mut changes = Vec::new();
+    let outcome = catch_unwind(AssertUnwindSafe(|| f(store, &mut changes)));
+    match outcome {
+        Ok(outcome) => (outcome, changes),
+        Err(payload) => {
+            for change in changes.into_iter().rev() {
+                match change {
+                    Change::Created(id) => {
+                        store.remove(id);
+                    }
+                    Change::Updated(ticket) => {
+                        let id = ticket.id;
+                        store[id] = ticket;
+                    }
+                }
+            }
+            resume_unwind(payload)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ticket_store::data::Status;
+    use ticket_store::test_helpers::ticket_draft;
+
+    #[test]
+    fn a_failed_batch_is_rolled_back() {
+        let mut store = TicketStore::new();
+        let existing = store.add_ticket(ticket_draft());
+
+        let patch = TicketPatch {
+            id: existing,
+            title: None,
+            description: None,
+            status: Some(Status::Done),
+        };
+        let outcome = catch_unwind(AssertUnwindSafe(|| {
+            atomically(&mut store, |store, changes| {
+                apply(store, BatchOp::Insert(ticket_draft()), changes);
+                apply(store, BatchOp::Update(patch), changes);
+                panic!("Crash requested");
+            })
+        }));
+        assert!(outcome.is_err());
+
+        assert_eq!(store.len(), 1);
+        assert_eq!(store[existing].status, Status::ToDo);
+    }
+}
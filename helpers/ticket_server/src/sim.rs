@@ -0,0 +1,544 @@
+//! A deterministic test harness for the server.
+//!
+//! Calls go through [`TicketStoreClient`]s to a server running on its own thread,
+//! in an order picked by a seeded scheduler: each step either sends a client's
+//! next command, or waits for the reply to the one it sent before.
+//! Every schedule produces a history of calls, which must be linearizable:
+//! there must be a sequential order of the calls, consistent with their
+//! real-time order, that a plain ticket store would agree with.
+//!
+//! A failing schedule can be replayed with `SIM_SEED=<seed> cargo test -p ticket_server sim`.
+use crate::client::{SendMode, TicketStoreClient};
+use crate::handle::{launch_with, Config};
+use crate::lanes::Priority;
+use crate::server::{Command, Get, Insert, Update};
+use actor::{Envelope, Pending};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::fmt;
+use ticket_fields::test_helpers::ticket_description;
+use ticket_fields::TicketTitle;
+use ticket_store::data::{Status, Ticket, TicketDraft, TicketId, TicketPatch};
+
+/// SplitMix64: small, fast, and good enough to pick schedules.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn chance(&mut self, one_in: usize) -> bool {
+        self.below(one_in) == 0
+    }
+}
+
+/// Titles are drawn from a small set, so that the model can tell them apart cheaply.
+fn title(label: usize) -> TicketTitle {
+    TicketTitle::try_from(format!("Ticket #{label}")).unwrap()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Op {
+    Insert {
+        title: usize,
+    },
+    Get {
+        id: TicketId,
+    },
+    Update {
+        id: TicketId,
+        title: Option<usize>,
+        status: Option<Status>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Outcome {
+    Inserted(TicketId),
+    Got(Option<Ticket>),
+    Updated(Option<Ticket>),
+}
+
+/// A completed call, with the logical times at which it was sent and answered.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Call {
+    pub(crate) client: usize,
+    pub(crate) op: Op,
+    pub(crate) outcome: Outcome,
+    pub(crate) invoked: usize,
+    pub(crate) returned: usize,
+}
+
+impl fmt::Display for Call {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let outcome = match &self.outcome {
+            Outcome::Got(Some(ticket)) => format!("Got({:?}, {:?})", ticket.title, ticket.status),
+            Outcome::Updated(Some(ticket)) => {
+                format!("Updated({:?}, {:?})", ticket.title, ticket.status)
+            }
+            outcome => format!("{outcome:?}"),
+        };
+        write!(
+            f,
+            "[{:>3}, {:>3}] client {}: {:?} -> {}",
+            self.invoked, self.returned, self.client, self.op, outcome
+        )
+    }
+}
+
+/// What a sequential ticket store would do.
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+struct Model {
+    next_id: u64,
+    tickets: BTreeMap<TicketId, (usize, Status)>,
+}
+
+impl Model {
+    /// Apply `op`, telling whether the store could have answered `outcome`.
+    fn apply(&mut self, op: &Op, outcome: &Outcome) -> bool {
+        match (op, outcome) {
+            (Op::Insert { title }, Outcome::Inserted(id)) => {
+                let expected = TicketId::from(self.next_id);
+                self.next_id += 1;
+                self.tickets.insert(expected, (*title, Status::ToDo));
+                *id == expected
+            }
+            (Op::Get { id }, Outcome::Got(ticket)) => self.has(*id, ticket),
+            (
+                Op::Update {
+                    id,
+                    title: new_title,
+                    status,
+                },
+                Outcome::Updated(ticket),
+            ) => {
+                if let Some((label, current)) = self.tickets.get_mut(id) {
+                    *label = new_title.unwrap_or(*label);
+                    *current = status.unwrap_or(*current);
+                }
+                self.has(*id, ticket)
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether `ticket` is what the store holds at `id`.
+    fn has(&self, id: TicketId, ticket: &Option<Ticket>) -> bool {
+        match (self.tickets.get(&id), ticket) {
+            (None, None) => true,
+            (Some((label, status)), Some(ticket)) => {
+                ticket.id == id && ticket.title == title(*label) && ticket.status == *status
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Look for a sequential order of `history` that the model agrees with.
+pub(crate) fn check_linearizable(history: &[Call]) -> Result<(), String> {
+    assert!(history.len() <= 64, "Histories are capped at 64 calls");
+    let mut visited = HashSet::new();
+    if linearize(history, 0, &Model::default(), &mut visited) {
+        Ok(())
+    } else {
+        let calls: Vec<_> = history.iter().map(|call| call.to_string()).collect();
+        Err(format!(
+            "The history isn't linearizable:\n{}",
+            calls.join("\n")
+        ))
+    }
+}
+
+/// `done` has a bit set for every call that was already put in order.
+fn linearize(
+    history: &[Call],
+    done: u64,
+    model: &Model,
+    visited: &mut HashSet<(u64, Model)>,
+) -> bool {
+    if done.count_ones() as usize == history.len() {
+        return true;
+    }
+    if !visited.insert((done, model.clone())) {
+        return false;
+    }
+    let pending = || (0..history.len()).filter(move |i| done & (1 << i) == 0);
+    // A call can go next only if it was sent before every other pending call returned.
+    let horizon = pending().map(|i| history[i].returned).min().unwrap();
+    pending()
+        .filter(|i| history[*i].invoked < horizon)
+        .any(|i| {
+            let mut next = model.clone();
+            next.apply(&history[i].op, &history[i].outcome)
+                && linearize(history, done | (1 << i), &next, visited)
+        })
+}
+
+/// Where a client waits for the reply to the command it sent.
+enum Reply {
+    Inserted(Pending<TicketId>),
+    Got(Pending<Option<Ticket>>),
+    Updated(Pending<Option<Ticket>>),
+}
+
+impl Reply {
+    fn wait(self) -> Outcome {
+        let outcome = match self {
+            Reply::Inserted(pending) => pending.recv().map(Outcome::Inserted),
+            Reply::Got(pending) => pending.recv().map(Outcome::Got),
+            Reply::Updated(pending) => pending.recv().map(Outcome::Updated),
+        };
+        outcome.expect("The server never fails in a simulation")
+    }
+}
+
+fn command(op: &Op) -> (Command, Reply) {
+    match op.clone() {
+        Op::Insert { title: label } => {
+            let draft = TicketDraft {
+                title: title(label),
+                description: ticket_description(),
+            };
+            let (command, pending) = Envelope::new(Insert(draft));
+            (command, Reply::Inserted(pending))
+        }
+        Op::Get { id } => {
+            let (command, pending) = Envelope::read(Get(id));
+            (command, Reply::Got(pending))
+        }
+        Op::Update {
+            id,
+            title: label,
+            status,
+        } => {
+            let patch = TicketPatch {
+                id,
+                title: label.map(title),
+                description: None,
+                status,
+            };
+            let (command, pending) = Envelope::new(Update(patch));
+            (command, Reply::Updated(pending))
+        }
+    }
+}
+
+struct SimClient {
+    client: TicketStoreClient,
+    script: VecDeque<Op>,
+    pending: Option<(Op, Reply, usize)>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Simulation {
+    pub(crate) clients: usize,
+    pub(crate) calls_per_client: usize,
+    /// Small queues make clients wait for room.
+    pub(crate) capacity: usize,
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Self {
+            clients: 4,
+            calls_per_client: 8,
+            capacity: 2,
+        }
+    }
+}
+
+enum Step {
+    Send(usize),
+    Receive(usize),
+}
+
+impl Simulation {
+    /// Run the schedule picked by `seed`, returning the calls in the order they returned.
+    pub(crate) fn run(&self, seed: u64) -> Vec<Call> {
+        let mut rng = Rng(seed);
+        let server = launch_with(Config {
+            capacity: self.capacity,
+            low_priority_capacity: self.capacity,
+            high_priority_weight: 1 + rng.below(4),
+            ..Config::default()
+        });
+
+        let ids = self.clients * self.calls_per_client / 3 + 1;
+        let mut clients: Vec<_> = (0..self.clients)
+            .map(|_| {
+                let priority = if rng.chance(2) {
+                    Priority::High
+                } else {
+                    Priority::Low
+                };
+                let script = (0..self.calls_per_client)
+                    .map(|_| match rng.below(3) {
+                        0 => Op::Insert {
+                            title: rng.below(4),
+                        },
+                        1 => Op::Get {
+                            id: TicketId::from(rng.below(ids) as u64),
+                        },
+                        _ => Op::Update {
+                            id: TicketId::from(rng.below(ids) as u64),
+                            title: rng.chance(2).then(|| rng.below(4)),
+                            status: rng
+                                .chance(2)
+                                .then(|| Status::ALL[rng.below(Status::ALL.len())]),
+                        },
+                    })
+                    .collect();
+                SimClient {
+                    client: server.client().with_priority(priority),
+                    script,
+                    pending: None,
+                }
+            })
+            .collect();
+
+        let mut history = Vec::new();
+        for now in 0.. {
+            let mut steps = Vec::new();
+            for (i, client) in clients.iter().enumerate() {
+                match &client.pending {
+                    None if !client.script.is_empty() => steps.push(Step::Send(i)),
+                    Some(_) => steps.push(Step::Receive(i)),
+                    None => {}
+                }
+            }
+            if steps.is_empty() {
+                break;
+            }
+
+            match steps.swap_remove(rng.below(steps.len())) {
+                Step::Send(i) => {
+                    let client = &mut clients[i];
+                    let op = client.script.front().unwrap().clone();
+                    let (command, reply) = command(&op);
+                    // Blocking rather than failing fast: whether a queue is full
+                    // depends on how far the server got, which the seed doesn't pick.
+                    client
+                        .client
+                        .send(command, SendMode::Block)
+                        .expect("The server never fails in a simulation");
+                    client.script.pop_front();
+                    client.pending = Some((op, reply, now));
+                }
+                Step::Receive(i) => {
+                    let (op, reply, invoked) = clients[i].pending.take().unwrap();
+                    history.push(Call {
+                        client: i,
+                        op,
+                        outcome: reply.wait(),
+                        invoked,
+                        returned: now,
+                    });
+                }
+            }
+        }
+        drop(clients);
+        server
+            .shutdown()
+            .expect("The server never fails in a simulation");
+        history
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handle::launch_pool;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn seeds() -> Vec<u64> {
+        match std::env::var("SIM_SEED") {
+            Ok(seed) => vec![seed.parse().expect("SIM_SEED must be a number")],
+            Err(_) => (0..300).collect(),
+        }
+    }
+
+    #[test]
+    fn seeded_schedules_are_linearizable() {
+        let simulation = Simulation::default();
+        for seed in seeds() {
+            let history = simulation.run(seed);
+            assert_eq!(
+                history.len(),
+                simulation.clients * simulation.calls_per_client
+            );
+            if let Err(e) = check_linearizable(&history) {
+                panic!("{e}\nReplay it with SIM_SEED={seed}");
+            }
+        }
+    }
+
+    #[test]
+    fn schedules_are_reproducible() {
+        let simulation = Simulation::default();
+        let timeline = |history: Vec<Call>| -> Vec<_> {
+            history
+                .into_iter()
+                .map(|call| (call.client, call.op, call.invoked, call.returned))
+                .collect()
+        };
+        for seed in [1, 42, 1337] {
+            assert_eq!(
+                timeline(simulation.run(seed)),
+                timeline(simulation.run(seed))
+            );
+        }
+        assert_ne!(timeline(simulation.run(1)), timeline(simulation.run(2)));
+    }
+
+    fn call(client: usize, op: Op, outcome: Outcome, invoked: usize, returned: usize) -> Call {
+        Call {
+            client,
+            op,
+            outcome,
+            invoked,
+            returned,
+        }
+    }
+
+    fn ticket(id: u64, label: usize, status: Status) -> Ticket {
+        Ticket {
+            id: TicketId::from(id),
+            title: title(label),
+            description: ticket_description(),
+            status,
+            created_at: std::time::SystemTime::now(),
+            resolved_at: None,
+        }
+    }
+
+    #[test]
+    fn overlapping_calls_can_go_either_way() {
+        let id = TicketId::from(0);
+        let history = [
+            call(0, Op::Insert { title: 1 }, Outcome::Inserted(id), 0, 10),
+            // Sent before the insert returned: it may or may not see it.
+            call(1, Op::Get { id }, Outcome::Got(None), 1, 2),
+            call(
+                2,
+                Op::Get { id },
+                Outcome::Got(Some(ticket(0, 1, Status::ToDo))),
+                3,
+                4,
+            ),
+        ];
+        assert!(check_linearizable(&history).is_ok());
+    }
+
+    #[test]
+    fn detects_stale_reads() {
+        let id = TicketId::from(0);
+        let update = Op::Update {
+            id,
+            title: None,
+            status: Some(Status::Done),
+        };
+        let history = [
+            call(0, Op::Insert { title: 1 }, Outcome::Inserted(id), 0, 1),
+            call(
+                0,
+                update,
+                Outcome::Updated(Some(ticket(0, 1, Status::Done))),
+                2,
+                3,
+            ),
+            // Sent after the update returned, yet doesn't see it.
+            call(
+                1,
+                Op::Get { id },
+                Outcome::Got(Some(ticket(0, 1, Status::ToDo))),
+                4,
+                5,
+            ),
+        ];
+        assert!(check_linearizable(&history).is_err());
+    }
+
+    #[test]
+    fn detects_reused_ids() {
+        let id = TicketId::from(0);
+        let history = [
+            call(0, Op::Insert { title: 1 }, Outcome::Inserted(id), 0, 2),
+            call(1, Op::Insert { title: 2 }, Outcome::Inserted(id), 1, 3),
+        ];
+        assert!(check_linearizable(&history).is_err());
+    }
+
+    /// The same check, on real threads against the worker pool:
+    /// not reproducible, but it covers what the simulation can't.
+    #[test]
+    fn threaded_histories_are_linearizable() {
+        let clock = Arc::new(AtomicUsize::new(0));
+        for _ in 0..20 {
+            let server = launch_pool(4, 2);
+            let threads: Vec<_> = (0..4)
+                .map(|i| {
+                    let client = server.client().with_send_mode(SendMode::Block);
+                    let clock = clock.clone();
+                    std::thread::spawn(move || {
+                        let mut rng = Rng(i as u64);
+                        (0..8)
+                            .map(|_| {
+                                let id = TicketId::from(rng.below(4) as u64);
+                                let op = match rng.below(3) {
+                                    0 => Op::Insert {
+                                        title: rng.below(4),
+                                    },
+                                    1 => Op::Get { id },
+                                    _ => Op::Update {
+                                        id,
+                                        title: Some(rng.below(4)),
+                                        status: None,
+                                    },
+                                };
+                                let invoked = clock.fetch_add(1, Ordering::SeqCst);
+                                let outcome = match &op {
+                                    Op::Insert { title: label } => client
+                                        .insert(TicketDraft {
+                                            title: title(*label),
+                                            description: ticket_description(),
+                                        })
+                                        .map(Outcome::Inserted),
+                                    Op::Get { id } => client.get(*id).map(Outcome::Got),
+                                    Op::Update {
+                                        id,
+                                        title: label,
+                                        status,
+                                    } => client
+                                        .update(TicketPatch {
+                                            id: *id,
+                                            title: label.map(title),
+                                            description: None,
+                                            status: *status,
+                                        })
+                                        .map(Outcome::Updated),
+                                };
+                                let returned = clock.fetch_add(1, Ordering::SeqCst);
+                                call(i, op, outcome.unwrap(), invoked, returned)
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            let history: Vec<_> = threads
+                .into_iter()
+                .flat_map(|thread| thread.join().unwrap())
+                .collect();
+            check_linearizable(&history).unwrap();
+            server.shutdown().unwrap();
+        }
+    }
+}
@@ -0,0 +1,240 @@
+//! The store, shared with other processes over a Unix domain socket.
+//!
+//! [`serve`] accepts connections and forwards each request to the in-process
+//! server through a [`TicketStoreClient`], so everything it does still
+//! applies: priorities, restarts, metrics. [`SocketClient`] has the same
+//! calls as [`TicketStoreClient`], minus the ones that only make sense
+//! within a process.
+use crate::client::{ClientError, TicketStoreClient};
+use crate::server::{BatchOp, BatchResponse};
+use crate::wire::{read_frame, write_frame, Reply, Request, Wire};
+use std::io;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use ticket_store::data::{Ticket, TicketDraft, TicketId, TicketPatch};
+
+/// Accepts connections on a socket, each served by a thread of its own.
+pub struct SocketServer {
+    path: PathBuf,
+    stopping: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+/// Serve the store behind `client` on a new socket at `path`.
+pub fn serve(path: impl AsRef<Path>, client: TicketStoreClient) -> io::Result<SocketServer> {
+    let path = path.as_ref().to_path_buf();
+    let listener = UnixListener::bind(&path)?;
+    let stopping = Arc::new(AtomicBool::new(false));
+    let thread = {
+        let stopping = stopping.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                if stopping.load(Ordering::Acquire) {
+                    break;
+                }
+                match stream {
+                    Ok(stream) => {
+                        let client = client.clone();
+                        std::thread::spawn(move || {
+                            if let Err(e) = serve_connection(&stream, &client) {
+                                tracing::debug!(%e, "Connection closed");
+                            }
+                        });
+                    }
+                    Err(e) => tracing::warn!(%e, "Failed to accept a connection"),
+                }
+            }
+        })
+    };
+    Ok(SocketServer {
+        path,
+        stopping,
+        thread,
+    })
+}
+
+impl SocketServer {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Stop accepting connections and remove the socket.
+    ///
+    /// Connections that are already open are served until their client
+    /// hangs up. The store itself keeps running.
+    pub fn shutdown(self) -> io::Result<()> {
+        self.stopping.store(true, Ordering::Release);
+        // Wake the acceptor up, so that it sees the flag.
+        let _ = UnixStream::connect(&self.path);
+        let _ = self.thread.join();
+        std::fs::remove_file(&self.path)
+    }
+}
+
+fn serve_connection(mut stream: &UnixStream, client: &TicketStoreClient) -> io::Result<()> {
+    while let Some(frame) = read_frame(&mut stream)? {
+        let reply = match Request::from_bytes(&frame) {
+            Ok(request) => handle(client, request),
+            // The frame was skipped as a whole: the connection is still usable.
+            Err(e) => {
+                tracing::warn!(%e, "Malformed request");
+                Err(ClientError::Malformed)
+            }
+        };
+        write_frame(&mut stream, &reply.to_bytes())?;
+    }
+    Ok(())
+}
+
+fn handle(client: &TicketStoreClient, request: Request) -> Result<Reply, ClientError> {
+    match request {
+        Request::Insert(draft) => client.insert(draft).map(Reply::Inserted),
+        Request::Get(id) => client.get(id).map(Reply::Got),
+        Request::Update(patch) => client.update(patch).map(Reply::Updated),
+        Request::Batch(ops) => client.batch(ops).map(Reply::Batch),
+    }
+}
+
+/// A connection to a [`SocketServer`], possibly from another process.
+///
+/// If a call fails halfway through, e.g. because it timed out, the connection
+/// is dropped: the next call opens a new one.
+pub struct SocketClient {
+    path: PathBuf,
+    timeout: Option<Duration>,
+    stream: Mutex<Option<UnixStream>>,
+}
+
+impl SocketClient {
+    /// Connect to the socket at `path`. Every call waits at most
+    /// [`TicketStoreClient::DEFAULT_TIMEOUT`] for the reply.
+    pub fn connect(path: impl AsRef<Path>) -> io::Result<Self> {
+        let client = Self {
+            path: path.as_ref().to_path_buf(),
+            timeout: Some(TicketStoreClient::DEFAULT_TIMEOUT),
+            stream: Mutex::new(None),
+        };
+        let stream = client.open()?;
+        *client.stream.lock().unwrap_or_else(PoisonError::into_inner) = Some(stream);
+        Ok(client)
+    }
+
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with(Some(timeout))
+    }
+
+    pub fn without_timeout(self) -> Self {
+        self.with(None)
+    }
+
+    fn with(self, timeout: Option<Duration>) -> Self {
+        let stream = self
+            .stream
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(stream) = &stream {
+            // If it fails, the next call does: the connection is reopened then.
+            let _ = stream.set_read_timeout(timeout);
+            let _ = stream.set_write_timeout(timeout);
+        }
+        Self {
+            path: self.path,
+            timeout,
+            stream: Mutex::new(stream),
+        }
+    }
+
+    fn open(&self) -> io::Result<UnixStream> {
+        let stream = UnixStream::connect(&self.path)?;
+        stream.set_read_timeout(self.timeout)?;
+        stream.set_write_timeout(self.timeout)?;
+        Ok(stream)
+    }
+
+    pub fn insert(&self, draft: TicketDraft) -> Result<TicketId, ClientError> {
+        match self.call(Request::Insert(draft))? {
+            Reply::Inserted(id) => Ok(id),
+            _ => Err(ClientError::Malformed),
+        }
+    }
+
+    pub fn get(&self, id: TicketId) -> Result<Option<Ticket>, ClientError> {
+        match self.call(Request::Get(id))? {
+            Reply::Got(ticket) => Ok(ticket),
+            _ => Err(ClientError::Malformed),
+        }
+    }
+
+    /// See [`TicketStoreClient::update`].
+    pub fn update(&self, patch: TicketPatch) -> Result<Option<Ticket>, ClientError> {
+        match self.call(Request::Update(patch))? {
+            Reply::Updated(ticket) => Ok(ticket),
+            _ => Err(ClientError::Malformed),
+        }
+    }
+
+    /// See [`TicketStoreClient::batch`].
+    pub fn batch(&self, ops: Vec<BatchOp>) -> Result<Vec<BatchResponse>, ClientError> {
+        match self.call(Request::Batch(ops))? {
+            Reply::Batch(responses) => Ok(responses),
+            _ => Err(ClientError::Malformed),
+        }
+    }
+
+    pub fn insert_many(
+        &self,
+        drafts: impl IntoIterator<Item = TicketDraft>,
+    ) -> Result<Vec<TicketId>, ClientError> {
+        let ops = drafts.into_iter().map(BatchOp::Insert).collect();
+        self.batch(ops)?
+            .into_iter()
+            .map(|response| match response {
+                BatchResponse::Inserted(id) => Ok(id),
+                _ => Err(ClientError::Malformed),
+            })
+            .collect()
+    }
+
+    pub fn get_many(
+        &self,
+        ids: impl IntoIterator<Item = TicketId>,
+    ) -> Result<Vec<Option<Ticket>>, ClientError> {
+        let ops = ids.into_iter().map(BatchOp::Get).collect();
+        self.batch(ops)?
+            .into_iter()
+            .map(|response| match response {
+                BatchResponse::Got(ticket) => Ok(ticket),
+                _ => Err(ClientError::Malformed),
+            })
+            .collect()
+    }
+
+    fn call(&self, request: Request) -> Result<Reply, ClientError> {
+        let mut stream = self.stream.lock().unwrap_or_else(PoisonError::into_inner);
+        if stream.is_none() {
+            *stream = Some(self.open().map_err(|_| ClientError::Disconnected)?);
+        }
+        match exchange(stream.as_ref().unwrap(), &request) {
+            Ok(reply) => reply,
+            Err(e) => {
+                // We can't tell where the stream stopped: start afresh next time.
+                *stream = None;
+                Err(match e.kind() {
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => ClientError::TimedOut,
+                    io::ErrorKind::InvalidData => ClientError::Malformed,
+                    _ => ClientError::Disconnected,
+                })
+            }
+        }
+    }
+}
+
+fn exchange(mut stream: &UnixStream, request: &Request) -> io::Result<Result<Reply, ClientError>> {
+    write_frame(&mut stream, &request.to_bytes())?;
+    let frame = read_frame(&mut stream)?.ok_or(io::ErrorKind::UnexpectedEof)?;
+    Wire::from_bytes(&frame).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
@@ -0,0 +1,192 @@
+use std::any::Any;
+use std::collections::VecDeque;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::time::{Duration, Instant};
+
+/// When to restart a crashed server, and how long to wait before doing so.
+///
+/// The server is given up on once it crashes more than `max_restarts` times
+/// within `window`. The pause before each restart starts at `backoff` and
+/// doubles after every crash, up to `max_backoff`; a run that outlives
+/// `window` brings it back to `backoff`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RestartPolicy {
+    pub max_restarts: usize,
+    pub window: Duration,
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl RestartPolicy {
+    /// Give up on the first crash.
+    pub fn never() -> Self {
+        Self {
+            max_restarts: 0,
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            max_restarts: 3,
+            window: Duration::from_secs(60),
+            backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Run `body` until it returns, restarting it whenever it panics.
+///
+/// `state` lives outside of the panic boundary: every restart resumes from it.
+/// `on_restart` is called before each restart.
+/// Returns the last panic payload if `policy` says to give up.
+pub(crate) fn supervise<S>(
+    policy: &RestartPolicy,
+    state: &mut S,
+    mut body: impl FnMut(&mut S),
+    mut on_restart: impl FnMut(),
+) -> Result<(), Box<dyn Any + Send>> {
+    let mut crashes = VecDeque::new();
+    let mut backoff = policy.backoff;
+    loop {
+        let started = Instant::now();
+        let payload = match catch_unwind(AssertUnwindSafe(|| body(state))) {
+            Ok(()) => return Ok(()),
+            Err(payload) => payload,
+        };
+
+        let now = Instant::now();
+        // Like the crashes it counts, the backoff only covers the last `window`.
+        if now.duration_since(started) > policy.window {
+            backoff = policy.backoff;
+        }
+        crashes.push_back(now);
+        while crashes
+            .front()
+            .is_some_and(|crash| now.duration_since(*crash) > policy.window)
+        {
+            crashes.pop_front();
+        }
+        if crashes.len() > policy.max_restarts {
+            return Err(payload);
+        }
+
+        std::thread::sleep(backoff);
+        backoff = (backoff * 2).min(policy.max_backoff);
+        on_restart();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(max_restarts: usize) -> RestartPolicy {
+        RestartPolicy {
+            max_restarts,
+            window: Duration::from_secs(60),
+            backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(4),
+        }
+    }
+
+    #[test]
+    fn restarts_from_the_current_state() {
+        let mut restarts = 0;
+        let mut runs = 0;
+        let outcome = supervise(
+            &policy(3),
+            &mut runs,
+            |runs| {
+                *runs += 1;
+                if *runs < 3 {
+                    panic!("Crash #{runs}");
+                }
+            },
+            || restarts += 1,
+        );
+        assert!(outcome.is_ok());
+        assert_eq!(runs, 3);
+        assert_eq!(restarts, 2);
+    }
+
+    #[test]
+    fn gives_up_after_too_many_crashes() {
+        let mut runs = 0;
+        let outcome = supervise(
+            &policy(2),
+            &mut runs,
+            |runs| {
+                *runs += 1;
+                panic!("Crash #{runs}");
+            },
+            || {},
+        );
+        let payload = outcome.unwrap_err();
+        assert_eq!(payload.downcast_ref::<String>().unwrap(), "Crash #3");
+    }
+
+    #[test]
+    fn old_crashes_are_forgotten() {
+        let policy = RestartPolicy {
+            window: Duration::from_millis(5),
+            max_backoff: Duration::from_millis(10),
+            backoff: Duration::from_millis(10),
+            ..policy(1)
+        };
+        let mut runs = 0;
+        let outcome = supervise(
+            &policy,
+            &mut runs,
+            |runs| {
+                *runs += 1;
+                if *runs < 5 {
+                    panic!("Crash #{runs}");
+                }
+            },
+            || {},
+        );
+        assert!(outcome.is_ok());
+    }
+
+    #[test]
+    fn a_long_run_resets_the_backoff() {
+        let policy = RestartPolicy {
+            max_restarts: 10,
+            window: Duration::from_millis(20),
+            backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(64),
+        };
+        let crashed_at = std::cell::Cell::new(Instant::now());
+        let mut pauses = Vec::new();
+        let mut runs = 0;
+        let outcome = supervise(
+            &policy,
+            &mut runs,
+            |runs| {
+                *runs += 1;
+                match *runs {
+                    1..=6 => {}
+                    7 => std::thread::sleep(Duration::from_millis(30)),
+                    _ => return,
+                }
+                crashed_at.set(Instant::now());
+                panic!("Crash #{runs}");
+            },
+            || pauses.push(crashed_at.get().elapsed()),
+        );
+        assert!(outcome.is_ok());
+        // 1, 2, 4, ... 32ms after the quick crashes, back to 1ms after the long run.
+        assert!(pauses[5] >= Duration::from_millis(32));
+        assert!(pauses[6] < Duration::from_millis(16));
+    }
+
+    #[test]
+    fn never_restarts() {
+        let outcome = supervise(&RestartPolicy::never(), &mut (), |_| panic!("Oops"), || {});
+        assert!(outcome.is_err());
+    }
+}
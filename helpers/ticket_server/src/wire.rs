@@ -0,0 +1,577 @@
+//! The binary format spoken over sockets.
+//!
+//! Every message is a frame: its length as a big-endian `u32`, then the
+//! payload. Integers are big-endian, strings are length-prefixed UTF-8,
+//! and enums start with a one-byte tag.
+use crate::client::ClientError;
+use crate::server::{BatchOp, BatchResponse};
+use std::io::{self, Read, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use ticket_fields::{TicketDescription, TicketTitle};
+use ticket_store::data::{Status, Ticket, TicketDraft, TicketId, TicketPatch};
+
+/// Bigger frames are rejected before their payload is read.
+pub(crate) const MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum WireError {
+    #[error("The message ended unexpectedly")]
+    Truncated,
+    #[error("Unknown tag {0} for {1}")]
+    UnknownTag(u8, &'static str),
+    #[error("Invalid field: {0}")]
+    Invalid(String),
+    #[error("Unexpected bytes after the end of the message")]
+    TrailingBytes,
+}
+
+pub(crate) fn write_frame(writer: &mut impl Write, payload: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(payload.len())
+        .ok()
+        .filter(|len| *len as usize <= MAX_FRAME_LEN)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "The frame is too large"))?;
+    writer.write_all(&len.to_be_bytes())?;
+    writer.write_all(payload)?;
+    writer.flush()
+}
+
+/// Returns `None` if the stream was closed in between two frames.
+pub(crate) fn read_frame(reader: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "The frame is too large",
+        ));
+    }
+    let mut payload = vec![0; len];
+    reader.read_exact(&mut payload)?;
+    Ok(Some(payload))
+}
+
+/// A value that can be sent over the wire.
+pub(crate) trait Wire: Sized {
+    fn encode(&self, out: &mut Vec<u8>);
+
+    /// Read a value from the front of `input`, advancing past it.
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError>;
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode(&mut out);
+        out
+    }
+
+    /// Decode a whole message: there must be nothing left afterwards.
+    fn from_bytes(mut input: &[u8]) -> Result<Self, WireError> {
+        let value = Self::decode(&mut input)?;
+        if input.is_empty() {
+            Ok(value)
+        } else {
+            Err(WireError::TrailingBytes)
+        }
+    }
+}
+
+fn take<'a>(input: &mut &'a [u8], n: usize) -> Result<&'a [u8], WireError> {
+    if input.len() < n {
+        return Err(WireError::Truncated);
+    }
+    let (head, tail) = input.split_at(n);
+    *input = tail;
+    Ok(head)
+}
+
+fn take_array<const N: usize>(input: &mut &[u8]) -> Result<[u8; N], WireError> {
+    Ok(take(input, N)?.try_into().unwrap())
+}
+
+impl Wire for u8 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        Ok(take_array::<1>(input)?[0])
+    }
+}
+
+impl Wire for u32 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_be_bytes());
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        Ok(u32::from_be_bytes(take_array(input)?))
+    }
+}
+
+impl Wire for u64 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_be_bytes());
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        Ok(u64::from_be_bytes(take_array(input)?))
+    }
+}
+
+impl Wire for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (self.len() as u32).encode(out);
+        out.extend_from_slice(self.as_bytes());
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        let len = u32::decode(input)? as usize;
+        let bytes = take(input, len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|e| WireError::Invalid(e.to_string()))
+    }
+}
+
+impl<T: Wire> Wire for Option<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            None => 0u8.encode(out),
+            Some(value) => {
+                1u8.encode(out);
+                value.encode(out);
+            }
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        match u8::decode(input)? {
+            0 => Ok(None),
+            1 => Ok(Some(T::decode(input)?)),
+            tag => Err(WireError::UnknownTag(tag, "Option")),
+        }
+    }
+}
+
+impl<T: Wire> Wire for Vec<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (self.len() as u32).encode(out);
+        for item in self {
+            item.encode(out);
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        let len = u32::decode(input)? as usize;
+        // Don't trust `len` for the allocation: the input may be lying.
+        let mut items = Vec::with_capacity(len.min(input.len()));
+        for _ in 0..len {
+            items.push(T::decode(input)?);
+        }
+        Ok(items)
+    }
+}
+
+impl Wire for SystemTime {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let since_epoch = self.duration_since(UNIX_EPOCH).unwrap_or_default();
+        since_epoch.as_secs().encode(out);
+        since_epoch.subsec_nanos().encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        let secs = u64::decode(input)?;
+        let nanos = u32::decode(input)?;
+        if nanos >= 1_000_000_000 {
+            return Err(WireError::Invalid(format!("{nanos} nanoseconds")));
+        }
+        UNIX_EPOCH
+            .checked_add(Duration::new(secs, nanos))
+            .ok_or_else(|| WireError::Invalid("The timestamp is out of range".into()))
+    }
+}
+
+impl Wire for TicketId {
+    fn encode(&self, out: &mut Vec<u8>) {
+        u64::from(*self).encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        Ok(u64::decode(input)?.into())
+    }
+}
+
+impl Wire for TicketTitle {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.as_str().to_owned().encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        TicketTitle::try_from(String::decode(input)?).map_err(|e| WireError::Invalid(e.to_string()))
+    }
+}
+
+impl Wire for TicketDescription {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.as_str().to_owned().encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        TicketDescription::try_from(String::decode(input)?)
+            .map_err(|e| WireError::Invalid(e.to_string()))
+    }
+}
+
+impl Wire for Status {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let tag: u8 = match self {
+            Status::ToDo => 0,
+            Status::InProgress => 1,
+            Status::Done => 2,
+        };
+        tag.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        match u8::decode(input)? {
+            0 => Ok(Status::ToDo),
+            1 => Ok(Status::InProgress),
+            2 => Ok(Status::Done),
+            tag => Err(WireError::UnknownTag(tag, "Status")),
+        }
+    }
+}
+
+impl Wire for TicketDraft {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.title.encode(out);
+        self.description.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        Ok(TicketDraft {
+            title: Wire::decode(input)?,
+            description: Wire::decode(input)?,
+        })
+    }
+}
+
+impl Wire for TicketPatch {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.id.encode(out);
+        self.title.encode(out);
+        self.description.encode(out);
+        self.status.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        Ok(TicketPatch {
+            id: Wire::decode(input)?,
+            title: Wire::decode(input)?,
+            description: Wire::decode(input)?,
+            status: Wire::decode(input)?,
+        })
+    }
+}
+
+impl Wire for Ticket {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.id.encode(out);
+        self.title.encode(out);
+        self.description.encode(out);
+        self.status.encode(out);
+        self.created_at.encode(out);
+        self.resolved_at.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        Ok(Ticket {
+            id: Wire::decode(input)?,
+            title: Wire::decode(input)?,
+            description: Wire::decode(input)?,
+            status: Wire::decode(input)?,
+            created_at: Wire::decode(input)?,
+            resolved_at: Wire::decode(input)?,
+        })
+    }
+}
+
+impl Wire for BatchOp {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            BatchOp::Insert(draft) => {
+                0u8.encode(out);
+                draft.encode(out);
+            }
+            BatchOp::Get(id) => {
+                1u8.encode(out);
+                id.encode(out);
+            }
+            BatchOp::Update(patch) => {
+                2u8.encode(out);
+                patch.encode(out);
+            }
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        match u8::decode(input)? {
+            0 => Ok(BatchOp::Insert(Wire::decode(input)?)),
+            1 => Ok(BatchOp::Get(Wire::decode(input)?)),
+            2 => Ok(BatchOp::Update(Wire::decode(input)?)),
+            tag => Err(WireError::UnknownTag(tag, "BatchOp")),
+        }
+    }
+}
+
+impl Wire for BatchResponse {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            BatchResponse::Inserted(id) => {
+                0u8.encode(out);
+                id.encode(out);
+            }
+            BatchResponse::Got(ticket) => {
+                1u8.encode(out);
+                ticket.encode(out);
+            }
+            BatchResponse::Updated(ticket) => {
+                2u8.encode(out);
+                ticket.encode(out);
+            }
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        match u8::decode(input)? {
+            0 => Ok(BatchResponse::Inserted(Wire::decode(input)?)),
+            1 => Ok(BatchResponse::Got(Wire::decode(input)?)),
+            2 => Ok(BatchResponse::Updated(Wire::decode(input)?)),
+            tag => Err(WireError::UnknownTag(tag, "BatchResponse")),
+        }
+    }
+}
+
+impl Wire for ClientError {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let tag: u8 = match self {
+            ClientError::Overloaded => 0,
+            ClientError::ShuttingDown => 1,
+            ClientError::Disconnected => 2,
+            ClientError::TimedOut => 3,
+            ClientError::ServerPanicked => 4,
+            ClientError::Malformed => 5,
+        };
+        tag.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        match u8::decode(input)? {
+            0 => Ok(ClientError::Overloaded),
+            1 => Ok(ClientError::ShuttingDown),
+            2 => Ok(ClientError::Disconnected),
+            3 => Ok(ClientError::TimedOut),
+            4 => Ok(ClientError::ServerPanicked),
+            5 => Ok(ClientError::Malformed),
+            tag => Err(WireError::UnknownTag(tag, "ClientError")),
+        }
+    }
+}
+
+impl<T: Wire> Wire for Result<T, ClientError> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Ok(value) => {
+                0u8.encode(out);
+                value.encode(out);
+            }
+            Err(e) => {
+                1u8.encode(out);
+                e.encode(out);
+            }
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        match u8::decode(input)? {
+            0 => Ok(Ok(T::decode(input)?)),
+            1 => Ok(Err(ClientError::decode(input)?)),
+            tag => Err(WireError::UnknownTag(tag, "Result")),
+        }
+    }
+}
+
+/// What a socket client asks for. Each request gets exactly one [`Reply`], in order.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Request {
+    Insert(TicketDraft),
+    Get(TicketId),
+    Update(TicketPatch),
+    Batch(Vec<BatchOp>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Reply {
+    Inserted(TicketId),
+    Got(Option<Ticket>),
+    Updated(Option<Ticket>),
+    Batch(Vec<BatchResponse>),
+}
+
+impl Wire for Request {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Request::Insert(draft) => {
+                0u8.encode(out);
+                draft.encode(out);
+            }
+            Request::Get(id) => {
+                1u8.encode(out);
+                id.encode(out);
+            }
+            Request::Update(patch) => {
+                2u8.encode(out);
+                patch.encode(out);
+            }
+            Request::Batch(ops) => {
+                3u8.encode(out);
+                ops.encode(out);
+            }
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        match u8::decode(input)? {
+            0 => Ok(Request::Insert(Wire::decode(input)?)),
+            1 => Ok(Request::Get(Wire::decode(input)?)),
+            2 => Ok(Request::Update(Wire::decode(input)?)),
+            3 => Ok(Request::Batch(Wire::decode(input)?)),
+            tag => Err(WireError::UnknownTag(tag, "Request")),
+        }
+    }
+}
+
+impl Wire for Reply {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Reply::Inserted(id) => {
+                0u8.encode(out);
+                id.encode(out);
+            }
+            Reply::Got(ticket) => {
+                1u8.encode(out);
+                ticket.encode(out);
+            }
+            Reply::Updated(ticket) => {
+                2u8.encode(out);
+                ticket.encode(out);
+            }
+            Reply::Batch(responses) => {
+                3u8.encode(out);
+                responses.encode(out);
+            }
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        match u8::decode(input)? {
+            0 => Ok(Reply::Inserted(Wire::decode(input)?)),
+            1 => Ok(Reply::Got(Wire::decode(input)?)),
+            2 => Ok(Reply::Updated(Wire::decode(input)?)),
+            3 => Ok(Reply::Batch(Wire::decode(input)?)),
+            tag => Err(WireError::UnknownTag(tag, "Reply")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ticket_fields::test_helpers::{ticket_description, ticket_title};
+    use ticket_store::test_helpers::ticket_draft;
+
+    fn round_trip<T: Wire + PartialEq + std::fmt::Debug>(value: T) {
+        assert_eq!(T::from_bytes(&value.to_bytes()).unwrap(), value);
+    }
+
+    fn ticket() -> Ticket {
+        Ticket {
+            id: TicketId::from(7),
+            title: ticket_title(),
+            description: ticket_description(),
+            status: Status::Done,
+            created_at: SystemTime::now(),
+            resolved_at: Some(SystemTime::now()),
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        let draft = ticket_draft();
+        let patch = TicketPatch {
+            id: TicketId::from(3),
+            title: None,
+            description: Some(ticket_description()),
+            status: Some(Status::InProgress),
+        };
+        round_trip(Request::Insert(draft.clone()));
+        round_trip(Request::Get(TicketId::from(u64::MAX)));
+        round_trip(Request::Update(patch.clone()));
+        round_trip(Request::Batch(vec![
+            BatchOp::Insert(draft),
+            BatchOp::Get(TicketId::from(1)),
+            BatchOp::Update(patch),
+        ]));
+
+        round_trip::<Result<Reply, ClientError>>(Ok(Reply::Got(Some(ticket()))));
+        round_trip::<Result<Reply, ClientError>>(Ok(Reply::Batch(vec![
+            BatchResponse::Inserted(TicketId::from(1)),
+            BatchResponse::Got(None),
+            BatchResponse::Updated(Some(ticket())),
+            BatchResponse::Updated(None),
+        ])));
+        round_trip::<Result<Reply, ClientError>>(Err(ClientError::Overloaded));
+    }
+
+    #[test]
+    fn rejects_malformed_messages() {
+        let bytes = Request::Get(TicketId::from(1)).to_bytes();
+        assert!(matches!(
+            Request::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(WireError::Truncated)
+        ));
+        assert!(matches!(
+            Request::from_bytes(&[bytes.as_slice(), &[0]].concat()),
+            Err(WireError::TrailingBytes)
+        ));
+        assert!(matches!(
+            Request::from_bytes(&[42]),
+            Err(WireError::UnknownTag(42, "Request"))
+        ));
+
+        // Titles are validated on the way in.
+        let mut bytes = vec![0];
+        String::new().encode(&mut bytes);
+        assert!(matches!(
+            Request::from_bytes(&bytes),
+            Err(WireError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn frames() {
+        let mut buffer = Vec::new();
+        write_frame(&mut buffer, b"hello").unwrap();
+        write_frame(&mut buffer, b"").unwrap();
+
+        let mut reader = buffer.as_slice();
+        assert_eq!(read_frame(&mut reader).unwrap().unwrap(), b"hello");
+        assert_eq!(read_frame(&mut reader).unwrap().unwrap(), b"");
+        assert!(read_frame(&mut reader).unwrap().is_none());
+
+        let too_large = (MAX_FRAME_LEN as u32 + 1).to_be_bytes();
+        assert!(read_frame(&mut too_large.as_slice()).is_err());
+    }
+}
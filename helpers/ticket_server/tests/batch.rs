@@ -0,0 +1,78 @@
+use ticket_server::data::{Status, TicketId, TicketPatch};
+use ticket_server::{launch, BatchOp, BatchResponse};
+use ticket_store::test_helpers::ticket_draft;
+
+#[test]
+fn many_tickets_in_one_round_trip() {
+    let server = launch(1);
+    let client = server.client();
+
+    let ids = client
+        .insert_many((0..10_000).map(|_| ticket_draft()))
+        .unwrap();
+    assert_eq!(ids.len(), 10_000);
+
+    let tickets = client.get_many(ids.iter().copied()).unwrap();
+    for (id, ticket) in ids.iter().zip(tickets) {
+        assert_eq!(ticket.unwrap().id, *id);
+    }
+}
+
+#[test]
+fn mixed_batch() {
+    let server = launch(1);
+    let client = server.client();
+    let id = client.insert(ticket_draft()).unwrap();
+
+    let responses = client
+        .batch(vec![
+            BatchOp::Update(TicketPatch {
+                id,
+                title: None,
+                description: None,
+                status: Some(Status::Done),
+            }),
+            BatchOp::Get(id),
+            BatchOp::Insert(ticket_draft()),
+        ])
+        .unwrap();
+
+    assert_eq!(responses.len(), 3);
+    match &responses[0] {
+        BatchResponse::Updated(Some(ticket)) => assert_eq!(ticket.status, Status::Done),
+        other => panic!("Unexpected response: {other:?}"),
+    }
+    match &responses[1] {
+        BatchResponse::Got(Some(ticket)) => assert_eq!(ticket.status, Status::Done),
+        other => panic!("Unexpected response: {other:?}"),
+    }
+    assert!(matches!(responses[2], BatchResponse::Inserted(new_id) if new_id != id));
+}
+
+#[test]
+fn updates_of_missing_tickets_are_reported_per_item() {
+    let server = launch(1);
+    let client = server.client();
+    let id = client.insert(ticket_draft()).unwrap();
+    let close = |id| {
+        BatchOp::Update(TicketPatch {
+            id,
+            title: None,
+            description: None,
+            status: Some(Status::Done),
+        })
+    };
+
+    let responses = client
+        .batch(vec![close(TicketId::from(42)), close(id)])
+        .unwrap();
+
+    assert_eq!(responses[0], BatchResponse::Updated(None));
+    assert!(matches!(&responses[1], BatchResponse::Updated(Some(ticket)) if ticket.id == id));
+}
+
+#[test]
+fn empty_batch() {
+    let server = launch(1);
+    assert_eq!(server.client().insert_many([]).unwrap(), vec![]);
+}
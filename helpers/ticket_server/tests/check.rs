@@ -0,0 +1,46 @@
+use ticket_server::data::{Status, TicketId, TicketPatch};
+use ticket_server::launch;
+use ticket_store::test_helpers::ticket_draft;
+
+#[test]
+fn works() {
+    let server = launch(5);
+    let client = server.client();
+    let draft = ticket_draft();
+    let ticket_id = client.insert(draft.clone()).unwrap();
+
+    let ticket = client.get(ticket_id).unwrap().unwrap();
+    assert_eq!(ticket_id, ticket.id);
+    assert_eq!(ticket.status, Status::ToDo);
+    assert_eq!(ticket.title, draft.title);
+    assert_eq!(ticket.description, draft.description);
+
+    let patch = TicketPatch {
+        id: ticket_id,
+        title: None,
+        description: None,
+        status: Some(Status::InProgress),
+    };
+    let updated = client.update(patch).unwrap().unwrap();
+    assert_eq!(updated.status, Status::InProgress);
+
+    let ticket = client.get(ticket_id).unwrap().unwrap();
+    assert_eq!(ticket.id, ticket_id);
+    assert_eq!(ticket.status, Status::InProgress);
+}
+
+#[test]
+fn updating_a_missing_ticket_finds_nothing() {
+    let server = launch(5);
+    let client = server.client();
+    let ticket_id = client.insert(ticket_draft()).unwrap();
+
+    let patch = TicketPatch {
+        id: TicketId::from(42),
+        title: None,
+        description: None,
+        status: Some(Status::Done),
+    };
+    assert_eq!(client.update(patch).unwrap(), None);
+    assert_eq!(client.get(ticket_id).unwrap().unwrap().status, Status::ToDo);
+}
@@ -0,0 +1,89 @@
+use ticket_server::data::{Status, TicketPatch};
+use ticket_server::{launch_pool, SendMode};
+use ticket_store::test_helpers::ticket_draft;
+
+#[test]
+fn works() {
+    let server = launch_pool(5, 4);
+    let client = server.client();
+    let draft = ticket_draft();
+    let ticket_id = client.insert(draft.clone()).unwrap();
+
+    let ticket = client.get(ticket_id).unwrap().unwrap();
+    assert_eq!(ticket_id, ticket.id);
+    assert_eq!(ticket.status, Status::ToDo);
+    assert_eq!(ticket.title, draft.title);
+    assert_eq!(ticket.description, draft.description);
+
+    let patch = TicketPatch {
+        id: ticket_id,
+        title: None,
+        description: None,
+        status: Some(Status::InProgress),
+    };
+    client.update(patch).unwrap();
+
+    let ticket = client.get(ticket_id).unwrap().unwrap();
+    assert_eq!(ticket.id, ticket_id);
+    assert_eq!(ticket.status, Status::InProgress);
+
+    server.shutdown().unwrap();
+}
+
+#[test]
+fn reads_see_earlier_writes() {
+    let server = launch_pool(16, 4);
+
+    let clients: Vec<_> = (0..8)
+        .map(|_| {
+            let client = server.client().with_send_mode(SendMode::Block);
+            std::thread::spawn(move || {
+                for _ in 0..200 {
+                    let id = client.insert(ticket_draft()).unwrap();
+                    client
+                        .update(TicketPatch {
+                            id,
+                            title: None,
+                            description: None,
+                            status: Some(Status::Done),
+                        })
+                        .unwrap();
+                    let ticket = client.get(id).unwrap().unwrap();
+                    assert_eq!(ticket.status, Status::Done);
+                }
+            })
+        })
+        .collect();
+    for client in clients {
+        client.join().unwrap();
+    }
+
+    server.shutdown().unwrap();
+}
+
+#[test]
+fn queued_reads_are_served_before_shutting_down() {
+    let server = launch_pool(64, 2);
+    let client = server.client().with_send_mode(SendMode::Block);
+    let id = client.insert(ticket_draft()).unwrap();
+    let accepted = client.stats().unwrap().accepted;
+
+    let readers: Vec<_> = (0..16)
+        .map(|_| {
+            let client = client.clone();
+            std::thread::spawn(move || client.get(id))
+        })
+        .collect();
+    // Wait for every read to be queued: each `stats` call counts too.
+    for polls in 1.. {
+        if client.stats().unwrap().accepted >= accepted + 16 + polls {
+            break;
+        }
+        std::thread::yield_now();
+    }
+    server.shutdown().unwrap();
+
+    for reader in readers {
+        assert_eq!(reader.join().unwrap().unwrap().unwrap().id, id);
+    }
+}
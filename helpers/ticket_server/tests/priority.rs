@@ -0,0 +1,59 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use ticket_server::{launch_with, ClientError, Config, Priority, SendMode};
+use ticket_store::test_helpers::ticket_draft;
+
+#[test]
+fn reads_keep_flowing_under_bulk_writes() {
+    let server = launch_with(Config {
+        capacity: 4,
+        low_priority_capacity: 4,
+        ..Config::default()
+    });
+    let id = server.client().insert(ticket_draft()).unwrap();
+    let done = Arc::new(AtomicBool::new(false));
+
+    let importers: Vec<_> = (0..4)
+        .map(|_| {
+            let client = server.client().with_priority(Priority::Low);
+            let done = done.clone();
+            std::thread::spawn(move || {
+                while !done.load(Ordering::Relaxed) {
+                    // Importers flood their own queue: they do get turned down.
+                    match client.insert_many((0..10).map(|_| ticket_draft())) {
+                        Ok(_) | Err(ClientError::Overloaded) => {}
+                        Err(e) => panic!("Unexpected error: {e}"),
+                    }
+                }
+            })
+        })
+        .collect();
+
+    let ui = server.client();
+    for _ in 0..1_000 {
+        assert_eq!(ui.get(id).unwrap().unwrap().id, id);
+    }
+
+    done.store(true, Ordering::Relaxed);
+    for importer in importers {
+        importer.join().unwrap();
+    }
+    server.shutdown().unwrap();
+}
+
+#[test]
+fn low_priority_commands_are_served_too() {
+    let server = launch_with(Config {
+        capacity: 4,
+        low_priority_capacity: 4,
+        high_priority_weight: 8,
+        ..Config::default()
+    });
+    let low = server
+        .client()
+        .with_priority(Priority::Low)
+        .with_send_mode(SendMode::Block);
+    let ids = low.insert_many((0..100).map(|_| ticket_draft())).unwrap();
+    assert_eq!(low.get_many(ids).unwrap().len(), 100);
+    server.shutdown().unwrap();
+}
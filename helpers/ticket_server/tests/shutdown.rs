@@ -0,0 +1,108 @@
+use std::sync::mpsc::channel;
+use std::time::Duration;
+use ticket_server::{
+    launch, launch_with, ClientError, Config, EventFilter, EventKind, Priority, SendMode,
+};
+use ticket_store::test_helpers::ticket_draft;
+
+#[test]
+fn rejects_calls_after_shutdown() {
+    let server = launch(5);
+    let client = server.client();
+    let id = client.insert(ticket_draft()).unwrap();
+
+    server.shutdown().unwrap();
+
+    assert_eq!(
+        client.insert(ticket_draft()),
+        Err(ClientError::ShuttingDown)
+    );
+    assert_eq!(client.get(id), Err(ClientError::ShuttingDown));
+}
+
+#[test]
+fn in_flight_calls_are_served_or_rejected() {
+    let server = launch(16);
+    let (started_sender, started) = channel();
+
+    let workers: Vec<_> = (0..4)
+        .map(|_| {
+            let client = server.client();
+            let started_sender = started_sender.clone();
+            std::thread::spawn(move || {
+                let _ = started_sender.send(());
+                let mut served = 0;
+                loop {
+                    match client.insert(ticket_draft()) {
+                        Ok(id) => {
+                            served += 1;
+                            // Whatever was inserted is still readable until the shutdown.
+                            match client.get(id) {
+                                Ok(ticket) => assert!(ticket.is_some()),
+                                Err(ClientError::ShuttingDown) => return served,
+                                Err(e) => panic!("Unexpected error: {e}"),
+                            }
+                        }
+                        Err(ClientError::Overloaded) => std::thread::yield_now(),
+                        Err(ClientError::ShuttingDown) => return served,
+                        Err(e) => panic!("Unexpected error: {e}"),
+                    }
+                }
+            })
+        })
+        .collect();
+
+    for _ in 0..4 {
+        started.recv().unwrap();
+    }
+    server.shutdown().unwrap();
+
+    for worker in workers {
+        worker.join().unwrap();
+    }
+}
+
+#[test]
+fn low_priority_calls_queued_before_shutdown_are_served() {
+    let server = launch_with(Config {
+        low_priority_capacity: 64,
+        ..Config::default()
+    });
+    let created = server
+        .client()
+        .subscribe(
+            EventFilter {
+                kind: Some(EventKind::Created),
+                ..EventFilter::default()
+            },
+            10_064,
+        )
+        .unwrap();
+    // Queue the calls without waiting for their replies: the events tell
+    // which were served.
+    let client = server
+        .client()
+        .with_timeout(Duration::ZERO)
+        .with_send_mode(SendMode::Block);
+    // Keeps the server busy while the low-priority calls queue up.
+    let _ = client.insert_many((0..10_000).map(|_| ticket_draft()));
+    let low = client.with_priority(Priority::Low);
+    for _ in 0..64 {
+        match low.insert(ticket_draft()) {
+            Ok(_) | Err(ClientError::TimedOut) => {}
+            Err(e) => panic!("Unexpected error: {e}"),
+        }
+    }
+
+    server.shutdown().unwrap();
+    assert_eq!(std::iter::from_fn(|| created.recv()).count(), 10_064);
+}
+
+#[test]
+fn shutdown_without_clients() {
+    let server = launch(1);
+    let client = server.client();
+    assert!(!server.is_finished());
+    drop(client);
+    server.shutdown().unwrap();
+}
@@ -0,0 +1,111 @@
+#![cfg(unix)]
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use ticket_server::data::{Status, TicketId, TicketPatch};
+use ticket_server::launch;
+use ticket_server::socket::{serve, SocketClient};
+use ticket_store::test_helpers::ticket_draft;
+
+fn socket_path() -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir().join(format!("ticket_server-{}-{n}.sock", std::process::id()))
+}
+
+#[test]
+fn works() {
+    let server = launch(5);
+    let socket = serve(socket_path(), server.client()).unwrap();
+    let client = SocketClient::connect(socket.path()).unwrap();
+
+    let draft = ticket_draft();
+    let ticket_id = client.insert(draft.clone()).unwrap();
+
+    let ticket = client.get(ticket_id).unwrap().unwrap();
+    assert_eq!(ticket_id, ticket.id);
+    assert_eq!(ticket.status, Status::ToDo);
+    assert_eq!(ticket.title, draft.title);
+    assert_eq!(ticket.description, draft.description);
+
+    let patch = TicketPatch {
+        id: ticket_id,
+        title: None,
+        description: None,
+        status: Some(Status::InProgress),
+    };
+    let updated = client.update(patch.clone()).unwrap().unwrap();
+    assert_eq!(updated.status, Status::InProgress);
+
+    let ticket = client.get(ticket_id).unwrap().unwrap();
+    assert_eq!(ticket.status, Status::InProgress);
+
+    let missing = TicketPatch {
+        id: TicketId::from(42),
+        ..patch
+    };
+    assert_eq!(client.update(missing).unwrap(), None);
+
+    socket.shutdown().unwrap();
+    server.shutdown().unwrap();
+}
+
+#[test]
+fn connections_share_the_store() {
+    let server = launch(16);
+    let socket = serve(socket_path(), server.client()).unwrap();
+
+    let clients: Vec<_> = (0..4)
+        .map(|_| {
+            let client = SocketClient::connect(socket.path()).unwrap();
+            std::thread::spawn(move || client.insert_many((0..25).map(|_| ticket_draft())).unwrap())
+        })
+        .collect();
+    let ids: Vec<_> = clients
+        .into_iter()
+        .flat_map(|client| client.join().unwrap())
+        .collect();
+
+    // Everything is visible from the in-process client too.
+    let tickets = server.client().get_many(ids).unwrap();
+    assert_eq!(tickets.len(), 100);
+    assert!(tickets.iter().all(Option::is_some));
+
+    socket.shutdown().unwrap();
+}
+
+#[test]
+fn malformed_requests_get_an_error_reply() {
+    let server = launch(5);
+    let socket = serve(socket_path(), server.client()).unwrap();
+    let mut stream = UnixStream::connect(socket.path()).unwrap();
+
+    // A one-byte frame with an unknown request tag.
+    stream.write_all(&[0, 0, 0, 1, 42]).unwrap();
+    let mut reply = [0; 6];
+    stream.read_exact(&mut reply).unwrap();
+    // An error, tagged as `Malformed`.
+    assert_eq!(reply, [0, 0, 0, 2, 1, 5]);
+
+    // The connection is still usable: get ticket #0, which doesn't exist.
+    stream
+        .write_all(&[0, 0, 0, 9, 1, 0, 0, 0, 0, 0, 0, 0, 0])
+        .unwrap();
+    let mut reply = [0; 7];
+    stream.read_exact(&mut reply).unwrap();
+    assert_eq!(reply, [0, 0, 0, 3, 0, 1, 0]);
+
+    socket.shutdown().unwrap();
+}
+
+#[test]
+fn shutdown_removes_the_socket() {
+    let server = launch(5);
+    let path = socket_path();
+    let socket = serve(&path, server.client()).unwrap();
+    socket.shutdown().unwrap();
+
+    assert!(!path.exists());
+    assert!(SocketClient::connect(&path).is_err());
+}
@@ -0,0 +1,59 @@
+use ticket_server::data::TicketId;
+use ticket_server::{launch, launch_pool, ClientError, CommandKind, SendMode};
+use ticket_store::test_helpers::ticket_draft;
+
+#[test]
+fn counts_handled_commands() {
+    let server = launch_pool(4, 2);
+    let client = server.client();
+    let id = client.insert(ticket_draft()).unwrap();
+    for _ in 0..3 {
+        client.get(id).unwrap();
+    }
+    client.get(TicketId::from(1000)).unwrap();
+
+    let stats = client.stats().unwrap();
+    assert_eq!(stats.commands[&CommandKind::Insert].handled, 1);
+    assert_eq!(stats.commands[&CommandKind::Get].handled, 4);
+    assert_eq!(stats.commands[&CommandKind::Get].latency.count(), 4);
+    assert_eq!(stats.commands[&CommandKind::Update].handled, 0);
+    assert_eq!(stats.accepted, 6);
+    assert_eq!(stats.backlog, 0);
+    assert_eq!(stats.rejection_rate(), 0.0);
+}
+
+#[test]
+fn reports_the_rejection_rate_under_load() {
+    let server = launch(1);
+
+    let clients: Vec<_> = (0..8)
+        .map(|_| {
+            let client = server.client();
+            std::thread::spawn(move || {
+                let mut overloaded = 0;
+                for _ in 0..500 {
+                    match client.insert(ticket_draft()) {
+                        Ok(_) => {}
+                        Err(ClientError::Overloaded) => overloaded += 1,
+                        Err(e) => panic!("Unexpected error: {e}"),
+                    }
+                }
+                overloaded
+            })
+        })
+        .collect();
+    let overloaded: u64 = clients.into_iter().map(|c| c.join().unwrap()).sum();
+
+    let stats = server
+        .client()
+        .with_send_mode(SendMode::Block)
+        .stats()
+        .unwrap();
+    assert_eq!(stats.overloaded, overloaded);
+    assert_eq!(
+        stats.commands[&CommandKind::Insert].handled,
+        8 * 500 - overloaded
+    );
+    let expected = overloaded as f64 / (stats.accepted + overloaded) as f64;
+    assert_eq!(stats.rejection_rate(), expected);
+}
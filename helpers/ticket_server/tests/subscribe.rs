@@ -0,0 +1,91 @@
+use std::time::Duration;
+use ticket_server::data::{Status, TicketPatch};
+use ticket_server::{launch, BatchOp, EventFilter, EventKind};
+use ticket_store::test_helpers::ticket_draft;
+
+fn close(id: ticket_server::data::TicketId) -> TicketPatch {
+    TicketPatch {
+        id,
+        title: None,
+        description: None,
+        status: Some(Status::Done),
+    }
+}
+
+#[test]
+fn pushes_inserts_and_updates() {
+    let server = launch(4);
+    let client = server.client();
+    let subscription = client.subscribe(EventFilter::default(), 8).unwrap();
+
+    let id = client.insert(ticket_draft()).unwrap();
+    client.update(close(id)).unwrap();
+
+    let created = subscription.recv().unwrap();
+    assert_eq!((created.id, created.kind), (id, EventKind::Created));
+    assert_eq!(created.ticket.status, Status::ToDo);
+
+    let updated = subscription.recv().unwrap();
+    assert_eq!((updated.id, updated.kind), (id, EventKind::Updated));
+    assert_eq!(updated.ticket.status, Status::Done);
+
+    server.shutdown().unwrap();
+    assert_eq!(subscription.recv(), None);
+}
+
+#[test]
+fn only_matching_events_are_pushed() {
+    let server = launch(4);
+    let client = server.client();
+    let closed = client
+        .subscribe(
+            EventFilter {
+                status: Some(Status::Done),
+                ..EventFilter::default()
+            },
+            8,
+        )
+        .unwrap();
+
+    let id = client.insert(ticket_draft()).unwrap();
+    client
+        .batch(vec![
+            BatchOp::Insert(ticket_draft()),
+            BatchOp::Update(close(id)),
+        ])
+        .unwrap();
+
+    let event = closed.recv_timeout(Duration::from_secs(1)).unwrap();
+    assert_eq!((event.id, event.kind), (id, EventKind::Updated));
+    assert!(closed.try_recv().is_err());
+}
+
+#[test]
+fn slow_subscribers_do_not_hold_up_the_server() {
+    let server = launch(4);
+    let client = server.client();
+    let slow = client.subscribe(EventFilter::default(), 1).unwrap();
+
+    let ids = client.insert_many((0..10).map(|_| ticket_draft())).unwrap();
+
+    assert_eq!(slow.recv().unwrap().id, ids[0]);
+    assert!(slow.try_recv().is_err());
+    assert_eq!(slow.missed(), 9);
+
+    // Unsubscribing is just dropping the subscription.
+    drop(slow);
+    client.insert(ticket_draft()).unwrap();
+}
+
+#[test]
+fn a_subscriber_with_no_capacity_still_gets_an_event() {
+    let server = launch(4);
+    let client = server.client();
+    let subscription = client.subscribe(EventFilter::default(), 0).unwrap();
+
+    let id = client.insert(ticket_draft()).unwrap();
+
+    let event = subscription.recv_timeout(Duration::from_secs(1)).unwrap();
+    assert_eq!((event.id, event.kind), (id, EventKind::Created));
+    assert_eq!(subscription.missed(), 0);
+}
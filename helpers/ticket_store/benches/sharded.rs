@@ -0,0 +1,118 @@
+//! Compares `ShardedTicketStore` with the channel-based server design of
+//! `07_threads/12_rw_lock`, where a single thread owns the store and every
+//! client call is a round trip over a bounded channel.
+//!
+//! Run with `cargo bench -p ticket_store`.
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use ticket_store::data::{Ticket, TicketDraft, TicketId};
+use ticket_store::test_helpers::ticket_draft;
+use ticket_store::{RwLockedTicketStore, ShardedTicketStore};
+
+const THREADS: usize = 8;
+const OPS_PER_THREAD: usize = 10_000;
+
+enum Command {
+    Insert {
+        draft: TicketDraft,
+        response_channel: SyncSender<TicketId>,
+    },
+    Get {
+        id: TicketId,
+        response_channel: SyncSender<Option<Arc<RwLock<Ticket>>>>,
+    },
+}
+
+#[derive(Clone)]
+struct TicketStoreClient {
+    sender: SyncSender<Command>,
+}
+
+impl TicketStoreClient {
+    fn insert(&self, draft: TicketDraft) -> TicketId {
+        let (response_sender, response_receiver) = sync_channel(1);
+        self.sender
+            .send(Command::Insert {
+                draft,
+                response_channel: response_sender,
+            })
+            .unwrap();
+        response_receiver.recv().unwrap()
+    }
+
+    fn get(&self, id: TicketId) -> Option<Arc<RwLock<Ticket>>> {
+        let (response_sender, response_receiver) = sync_channel(1);
+        self.sender
+            .send(Command::Get {
+                id,
+                response_channel: response_sender,
+            })
+            .unwrap();
+        response_receiver.recv().unwrap()
+    }
+}
+
+fn server(receiver: Receiver<Command>) {
+    let mut store = RwLockedTicketStore::new();
+    while let Ok(command) = receiver.recv() {
+        match command {
+            Command::Insert {
+                draft,
+                response_channel,
+            } => {
+                let _ = response_channel.send(store.add_ticket(draft));
+            }
+            Command::Get {
+                id,
+                response_channel,
+            } => {
+                let _ = response_channel.send(store.get(id).cloned());
+            }
+        }
+    }
+}
+
+/// Every thread inserts a ticket and immediately reads it back, `OPS_PER_THREAD` times.
+fn run(work: impl Fn() + Sync) -> Duration {
+    let start = Instant::now();
+    std::thread::scope(|scope| {
+        for _ in 0..THREADS {
+            scope.spawn(&work);
+        }
+    });
+    start.elapsed()
+}
+
+fn main() {
+    let sharded = ShardedTicketStore::new();
+    let sharded_time = run(|| {
+        for _ in 0..OPS_PER_THREAD {
+            let id = sharded.add_ticket(ticket_draft());
+            assert!(sharded.get(id).is_some());
+        }
+    });
+
+    let (sender, receiver) = sync_channel(128);
+    let server_thread = std::thread::spawn(move || server(receiver));
+    let client = TicketStoreClient { sender };
+    let channel_time = run(|| {
+        let client = client.clone();
+        for _ in 0..OPS_PER_THREAD {
+            let id = client.insert(ticket_draft());
+            assert!(client.get(id).is_some());
+        }
+    });
+    drop(client);
+    server_thread.join().unwrap();
+
+    let ops = (THREADS * OPS_PER_THREAD * 2) as f64;
+    println!("{THREADS} threads x {OPS_PER_THREAD} insert+get");
+    for (name, time) in [("sharded", sharded_time), ("channel", channel_time)] {
+        println!(
+            "{name:<8} {:>10.2?} {:>12.0} ops/s",
+            time,
+            ops / time.as_secs_f64()
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::{btree_map, hash_map, BTreeMap, HashMap};
+use std::hash::Hash;
+
+/// The container a [`Store`](crate::Store) keeps its entries in.
+///
+/// Implemented for `BTreeMap` (iteration ordered by key) and `HashMap`.
+pub trait Backing<K, V>: Default + IntoIterator<Item = (K, V)> {
+    type Iter<'a>: Iterator<Item = (&'a K, &'a V)>
+    where
+        Self: 'a,
+        K: 'a,
+        V: 'a;
+    type IterMut<'a>: Iterator<Item = (&'a K, &'a mut V)>
+    where
+        Self: 'a,
+        K: 'a,
+        V: 'a;
+
+    fn insert(&mut self, key: K, value: V) -> Option<V>;
+    fn get(&self, key: &K) -> Option<&V>;
+    fn get_mut(&mut self, key: &K) -> Option<&mut V>;
+    fn remove(&mut self, key: &K) -> Option<V>;
+    fn len(&self) -> usize;
+    fn iter(&self) -> Self::Iter<'_>;
+    fn iter_mut(&mut self) -> Self::IterMut<'_>;
+    fn retain(&mut self, f: impl FnMut(&K, &mut V) -> bool);
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<K: Ord, V> Backing<K, V> for BTreeMap<K, V> {
+    type Iter<'a>
+        = btree_map::Iter<'a, K, V>
+    where
+        K: 'a,
+        V: 'a;
+    type IterMut<'a>
+        = btree_map::IterMut<'a, K, V>
+    where
+        K: 'a,
+        V: 'a;
+
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        BTreeMap::insert(self, key, value)
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        BTreeMap::get(self, key)
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        BTreeMap::get_mut(self, key)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        BTreeMap::remove(self, key)
+    }
+
+    fn len(&self) -> usize {
+        BTreeMap::len(self)
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        BTreeMap::iter(self)
+    }
+
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        BTreeMap::iter_mut(self)
+    }
+
+    fn retain(&mut self, mut f: impl FnMut(&K, &mut V) -> bool) {
+        BTreeMap::retain(self, |key, value| f(key, value))
+    }
+}
+
+impl<K: Eq + Hash, V> Backing<K, V> for HashMap<K, V> {
+    type Iter<'a>
+        = hash_map::Iter<'a, K, V>
+    where
+        K: 'a,
+        V: 'a;
+    type IterMut<'a>
+        = hash_map::IterMut<'a, K, V>
+    where
+        K: 'a,
+        V: 'a;
+
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        HashMap::insert(self, key, value)
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        HashMap::get(self, key)
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        HashMap::get_mut(self, key)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        HashMap::remove(self, key)
+    }
+
+    fn len(&self) -> usize {
+        HashMap::len(self)
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        HashMap::iter(self)
+    }
+
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        HashMap::iter_mut(self)
+    }
+
+    fn retain(&mut self, mut f: impl FnMut(&K, &mut V) -> bool) {
+        HashMap::retain(self, |key, value| f(key, value))
+    }
+}
@@ -0,0 +1,111 @@
+use crate::store::FromDraft;
+use std::fmt;
+use std::time::SystemTime;
+use ticket_fields::{TicketDescription, TicketTitle};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TicketId(u64);
+
+impl From<u64> for TicketId {
+    fn from(value: u64) -> Self {
+        Self(value)
+    }
+}
+
+impl From<TicketId> for u64 {
+    fn from(value: TicketId) -> Self {
+        value.0
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ticket {
+    pub id: TicketId,
+    pub title: TicketTitle,
+    pub description: TicketDescription,
+    pub status: Status,
+    pub created_at: SystemTime,
+    /// When the ticket was last moved to [`Status::Done`], if it's still there.
+    pub resolved_at: Option<SystemTime>,
+}
+
+impl Ticket {
+    /// Change the status, keeping `resolved_at` in sync.
+    pub fn set_status(&mut self, status: Status) {
+        self.set_status_at(status, SystemTime::now());
+    }
+
+    pub fn set_status_at(&mut self, status: Status, at: SystemTime) {
+        match (self.status, status) {
+            (Status::Done, Status::Done) => {}
+            (_, Status::Done) => self.resolved_at = Some(at),
+            (_, _) => self.resolved_at = None,
+        }
+        self.status = status;
+    }
+
+    /// Change the fields `patch` sets, leaving the others as they are.
+    pub fn apply(&mut self, patch: TicketPatch) {
+        debug_assert_eq!(patch.id, self.id, "The patch is for another ticket");
+        if let Some(title) = patch.title {
+            self.title = title;
+        }
+        if let Some(description) = patch.description {
+            self.description = description;
+        }
+        if let Some(status) = patch.status {
+            self.set_status(status);
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TicketDraft {
+    pub title: TicketTitle,
+    pub description: TicketDescription,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TicketPatch {
+    pub id: TicketId,
+    pub title: Option<TicketTitle>,
+    pub description: Option<TicketDescription>,
+    pub status: Option<Status>,
+}
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
+pub enum Status {
+    ToDo,
+    InProgress,
+    Done,
+}
+
+impl Status {
+    pub const ALL: [Status; 3] = [Status::ToDo, Status::InProgress, Status::Done];
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Status::ToDo => "To-Do",
+            Status::InProgress => "In Progress",
+            Status::Done => "Done",
+        };
+        f.pad(label)
+    }
+}
+
+impl FromDraft<TicketId> for Ticket {
+    type Draft = TicketDraft;
+
+    fn from_draft(id: TicketId, draft: TicketDraft) -> Self {
+        Ticket {
+            id,
+            title: draft.title,
+            description: draft.description,
+            status: Status::ToDo,
+            created_at: SystemTime::now(),
+            resolved_at: None,
+        }
+    }
+}
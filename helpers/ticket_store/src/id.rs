@@ -0,0 +1,34 @@
+/// Hands out the key for every new entry of a [`Store`](crate::Store).
+///
+/// It must never hand out the same key twice, wrapping around included:
+/// the store panics rather than overwrite an entry.
+pub trait IdGenerator<K> {
+    fn next_id(&mut self) -> K;
+}
+
+/// The default generator: `0`, `1`, `2`, ... converted into the key type.
+#[derive(Clone, Debug, Default)]
+pub struct Sequential {
+    counter: u64,
+}
+
+impl Sequential {
+    /// Start counting from `first` instead of `0`.
+    pub fn starting_at(first: u64) -> Self {
+        Self { counter: first }
+    }
+}
+
+impl<K: From<u64>> IdGenerator<K> for Sequential {
+    fn next_id(&mut self) -> K {
+        let id = self.counter;
+        self.counter += 1;
+        id.into()
+    }
+}
+
+impl<K, F: FnMut() -> K> IdGenerator<K> for F {
+    fn next_id(&mut self) -> K {
+        self()
+    }
+}
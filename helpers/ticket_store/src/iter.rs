@@ -0,0 +1,206 @@
+use crate::backing::Backing;
+use crate::id::IdGenerator;
+use crate::store::{FromDraft, Store};
+use std::marker::PhantomData;
+
+/// Yields references to the stored values, in the order of the backing container.
+pub struct Iter<'a, K: 'a, V: 'a, B: Backing<K, V> + 'a> {
+    inner: B::Iter<'a>,
+}
+
+impl<'a, K, V, B: Backing<K, V>> Iter<'a, K, V, B> {
+    pub(crate) fn new(inner: B::Iter<'a>) -> Self {
+        Self { inner }
+    }
+}
+
+impl<'a, K, V, B: Backing<K, V>> Iterator for Iter<'a, K, V, B> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, value)| value)
+    }
+}
+
+/// Yields mutable references to the stored values.
+///
+/// Keys can't be reached through it, so ids stay the way the store assigned them.
+pub struct IterMut<'a, K: 'a, V: 'a, B: Backing<K, V> + 'a> {
+    inner: B::IterMut<'a>,
+}
+
+impl<'a, K, V, B: Backing<K, V>> IterMut<'a, K, V, B> {
+    pub(crate) fn new(inner: B::IterMut<'a>) -> Self {
+        Self { inner }
+    }
+}
+
+impl<'a, K, V, B: Backing<K, V>> Iterator for IterMut<'a, K, V, B> {
+    type Item = &'a mut V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, value)| value)
+    }
+}
+
+/// Yields the stored values by value, consuming the store.
+pub struct IntoIter<K, V, B: Backing<K, V>> {
+    inner: B::IntoIter,
+    _entry: PhantomData<fn() -> (K, V)>,
+}
+
+impl<K, V, B: Backing<K, V>> Iterator for IntoIter<K, V, B> {
+    type Item = V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, value)| value)
+    }
+}
+
+impl<'a, K, V, B, G> IntoIterator for &'a Store<K, V, B, G>
+where
+    K: Clone,
+    B: Backing<K, V>,
+    G: IdGenerator<K>,
+{
+    type Item = &'a V;
+    type IntoIter = Iter<'a, K, V, B>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K, V, B, G> IntoIterator for &'a mut Store<K, V, B, G>
+where
+    K: Clone,
+    B: Backing<K, V>,
+    G: IdGenerator<K>,
+{
+    type Item = &'a mut V;
+    type IntoIter = IterMut<'a, K, V, B>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<K, V, B, G> IntoIterator for Store<K, V, B, G>
+where
+    K: Clone,
+    B: Backing<K, V>,
+    G: IdGenerator<K>,
+{
+    type Item = V;
+    type IntoIter = IntoIter<K, V, B>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            inner: self.items.into_iter(),
+            _entry: PhantomData,
+        }
+    }
+}
+
+/// Drafts go through the store's id generator, exactly like `add_ticket`.
+impl<K, V, B, G> Extend<V::Draft> for Store<K, V, B, G>
+where
+    K: Clone,
+    V: FromDraft<K>,
+    B: Backing<K, V>,
+    G: IdGenerator<K>,
+{
+    fn extend<I: IntoIterator<Item = V::Draft>>(&mut self, drafts: I) {
+        for draft in drafts {
+            self.add_ticket(draft);
+        }
+    }
+}
+
+impl<K, V, B, G> FromIterator<V::Draft> for Store<K, V, B, G>
+where
+    K: Clone,
+    V: FromDraft<K>,
+    B: Backing<K, V>,
+    G: IdGenerator<K> + Default,
+{
+    fn from_iter<I: IntoIterator<Item = V::Draft>>(drafts: I) -> Self {
+        let mut store = Self::new();
+        store.extend(drafts);
+        store
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{Status, TicketDraft, TicketId};
+    use crate::test_helpers::ticket_draft;
+    use crate::TicketStore;
+
+    fn drafts(n: usize) -> impl Iterator<Item = TicketDraft> {
+        (0..n).map(|_| ticket_draft())
+    }
+
+    #[test]
+    fn collect_and_extend() {
+        let mut store: TicketStore = drafts(3).collect();
+        store.extend(drafts(2));
+
+        let ids: Vec<u64> = store.iter().map(|t| t.id.into()).collect();
+        assert_eq!(ids, vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn mutable_iteration() {
+        let mut store: TicketStore = drafts(4).collect();
+        for ticket in &mut store {
+            ticket.status = Status::InProgress;
+        }
+        store
+            .iter_mut()
+            .take(1)
+            .for_each(|t| t.status = Status::Done);
+
+        let statuses: Vec<Status> = (&store).into_iter().map(|t| t.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                Status::Done,
+                Status::InProgress,
+                Status::InProgress,
+                Status::InProgress
+            ]
+        );
+    }
+
+    #[test]
+    fn owned_iteration() {
+        let store: TicketStore = drafts(3).collect();
+        let tickets: Vec<_> = store.into_iter().collect();
+        assert_eq!(tickets.len(), 3);
+        assert_eq!(tickets[2].id, TicketId::from(2));
+    }
+
+    #[test]
+    fn retain_and_drain_filter() {
+        let mut store: TicketStore = drafts(6).collect();
+        store.retain(|t| {
+            if u64::from(t.id) % 2 == 0 {
+                t.status = Status::Done;
+            }
+            u64::from(t.id) < 5
+        });
+        assert_eq!(store.len(), 5);
+
+        let done = store.drain_filter(|t| t.status == Status::Done);
+        let done: Vec<u64> = done.into_iter().map(|t| t.id.into()).collect();
+        assert_eq!(done, vec![0, 2, 4]);
+
+        let left: Vec<u64> = store.iter().map(|t| t.id.into()).collect();
+        assert_eq!(left, vec![1, 3]);
+
+        // Ids are never reused, even after removals.
+        store.extend(drafts(1));
+        assert!(store.get(TicketId::from(6)).is_some());
+    }
+}
@@ -0,0 +1,59 @@
+//! The `TicketStore`s of `06_ticket_management` and `07_threads`, as a single
+//! generic [`Store`] plus type aliases for each variant.
+//!
+//! The exercises keep their own copies: implementing `Index`, `IndexMut` and
+//! `IntoIterator` by hand is what they're about, so they can't depend on a
+//! store that already does it. The helper crates build on this one instead.
+mod backing;
+pub mod data;
+mod id;
+mod iter;
+mod lock_order;
+mod parallel;
+pub mod poison;
+pub mod report;
+mod sharded;
+mod snapshot;
+mod store;
+pub mod test_helpers;
+mod transaction;
+
+pub use backing::Backing;
+pub use id::{IdGenerator, Sequential};
+pub use iter::{IntoIter, Iter, IterMut};
+pub use lock_order::{
+    TicketMutex, TicketMutexGuard, TicketReadGuard, TicketRwLock, TicketWriteGuard,
+};
+pub use parallel::Parallelism;
+pub use sharded::ShardedTicketStore;
+pub use snapshot::{Snapshot, SnapshotTicketStore, Writer};
+pub use store::{FromDraft, Store};
+pub use transaction::{Transaction, TransactionError};
+
+use crate::data::{Ticket, TicketId};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// Tickets ordered by id, as in `06_ticket_management/16_btreemap`.
+pub type TicketStore = Store<TicketId, Ticket>;
+
+/// Tickets in no particular order, as in `06_ticket_management/15_hashmap`.
+pub type HashTicketStore = Store<TicketId, Ticket, HashMap<TicketId, Ticket>>;
+
+/// Tickets handed out behind a `Mutex`, as in `07_threads/11_locks`.
+pub type LockedTicketStore =
+    Store<TicketId, Arc<Mutex<Ticket>>, BTreeMap<TicketId, Arc<Mutex<Ticket>>>>;
+
+/// Tickets handed out behind a `RwLock`, as in `07_threads/13_without_channels`.
+pub type RwLockedTicketStore =
+    Store<TicketId, Arc<RwLock<Ticket>>, BTreeMap<TicketId, Arc<RwLock<Ticket>>>>;
+
+/// Like [`LockedTicketStore`], checking lock ordering in debug builds:
+/// see [`TicketMutex`].
+pub type TrackedTicketStore =
+    Store<TicketId, Arc<TicketMutex>, BTreeMap<TicketId, Arc<TicketMutex>>>;
+
+/// Like [`RwLockedTicketStore`], checking lock ordering in debug builds:
+/// see [`TicketRwLock`].
+pub type TrackedRwTicketStore =
+    Store<TicketId, Arc<TicketRwLock>, BTreeMap<TicketId, Arc<TicketRwLock>>>;
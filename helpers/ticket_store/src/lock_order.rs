@@ -0,0 +1,607 @@
+//! Ticket locks that check, in debug builds, the order they are taken in.
+//!
+//! Two threads that lock the same two tickets in opposite orders can deadlock,
+//! but only if they happen to interleave just so. [`TicketMutex`] and
+//! [`TicketRwLock`] remember, across threads, which ticket was locked while
+//! holding which: as soon as a thread takes two tickets in the opposite order
+//! of an earlier acquisition, it panics with both ticket ids and both
+//! backtraces — whether or not the deadlock would have happened this time.
+//!
+//! To lock several tickets without thinking about it, use
+//! [`Store::lock_many`] (or [`Store::write_many`]): it always goes in
+//! `TicketId` order. In release builds the checks compile down to nothing.
+use crate::backing::Backing;
+use crate::data::{Ticket, TicketDraft, TicketId};
+use crate::id::IdGenerator;
+use crate::poison::{recover, TicketLockError};
+use crate::store::{FromDraft, Store};
+use std::collections::BTreeSet;
+use std::ops::{Deref, DerefMut};
+use std::sync::{
+    Arc, LockResult, Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard,
+};
+
+/// A `Mutex<Ticket>` that checks lock ordering in debug builds.
+#[derive(Debug)]
+pub struct TicketMutex {
+    tracker: Tracker,
+    ticket: Mutex<Ticket>,
+}
+
+/// A `RwLock<Ticket>` that checks lock ordering in debug builds.
+///
+/// Read locks are checked just like write locks: two readers don't block each
+/// other, but a writer queued in between can still close the cycle.
+#[derive(Debug)]
+pub struct TicketRwLock {
+    tracker: Tracker,
+    ticket: RwLock<Ticket>,
+}
+
+impl TicketMutex {
+    pub fn new(ticket: Ticket) -> Self {
+        Self {
+            tracker: Tracker::new(ticket.id),
+            ticket: Mutex::new(ticket),
+        }
+    }
+
+    pub fn id(&self) -> TicketId {
+        self.tracker.ticket
+    }
+
+    pub fn is_poisoned(&self) -> bool {
+        self.ticket.is_poisoned()
+    }
+
+    pub fn clear_poison(&self) {
+        self.ticket.clear_poison()
+    }
+
+    /// # Panics
+    ///
+    /// In debug builds, panics instead of blocking if this thread already
+    /// holds the lock, or if taking it could deadlock with another thread.
+    pub fn lock(&self) -> LockResult<TicketMutexGuard<'_>> {
+        let held = self.tracker.acquire();
+        wrap(self.ticket.lock(), |guard| TicketMutexGuard {
+            guard,
+            _held: held,
+        })
+    }
+}
+
+impl TicketRwLock {
+    pub fn new(ticket: Ticket) -> Self {
+        Self {
+            tracker: Tracker::new(ticket.id),
+            ticket: RwLock::new(ticket),
+        }
+    }
+
+    pub fn id(&self) -> TicketId {
+        self.tracker.ticket
+    }
+
+    pub fn is_poisoned(&self) -> bool {
+        self.ticket.is_poisoned()
+    }
+
+    pub fn clear_poison(&self) {
+        self.ticket.clear_poison()
+    }
+
+    /// # Panics
+    ///
+    /// See [`TicketMutex::lock`].
+    pub fn read(&self) -> LockResult<TicketReadGuard<'_>> {
+        let held = self.tracker.acquire();
+        wrap(self.ticket.read(), |guard| TicketReadGuard {
+            guard,
+            _held: held,
+        })
+    }
+
+    /// # Panics
+    ///
+    /// See [`TicketMutex::lock`].
+    pub fn write(&self) -> LockResult<TicketWriteGuard<'_>> {
+        let held = self.tracker.acquire();
+        wrap(self.ticket.write(), |guard| TicketWriteGuard {
+            guard,
+            _held: held,
+        })
+    }
+}
+
+fn wrap<G, T>(result: LockResult<G>, f: impl FnOnce(G) -> T) -> LockResult<T> {
+    match result {
+        Ok(guard) => Ok(f(guard)),
+        Err(poisoned) => Err(PoisonError::new(f(poisoned.into_inner()))),
+    }
+}
+
+impl FromDraft<TicketId> for Arc<TicketMutex> {
+    type Draft = TicketDraft;
+
+    fn from_draft(id: TicketId, draft: Self::Draft) -> Self {
+        Arc::new(TicketMutex::new(Ticket::from_draft(id, draft)))
+    }
+}
+
+impl FromDraft<TicketId> for Arc<TicketRwLock> {
+    type Draft = TicketDraft;
+
+    fn from_draft(id: TicketId, draft: Self::Draft) -> Self {
+        Arc::new(TicketRwLock::new(Ticket::from_draft(id, draft)))
+    }
+}
+
+// The wrapped guard comes first, so that the lock is released before it's
+// forgotten about.
+pub struct TicketMutexGuard<'a> {
+    guard: MutexGuard<'a, Ticket>,
+    _held: Held<'a>,
+}
+
+pub struct TicketReadGuard<'a> {
+    guard: RwLockReadGuard<'a, Ticket>,
+    _held: Held<'a>,
+}
+
+pub struct TicketWriteGuard<'a> {
+    guard: RwLockWriteGuard<'a, Ticket>,
+    _held: Held<'a>,
+}
+
+impl Deref for TicketMutexGuard<'_> {
+    type Target = Ticket;
+
+    fn deref(&self) -> &Ticket {
+        &self.guard
+    }
+}
+
+impl DerefMut for TicketMutexGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Ticket {
+        &mut self.guard
+    }
+}
+
+impl Deref for TicketReadGuard<'_> {
+    type Target = Ticket;
+
+    fn deref(&self) -> &Ticket {
+        &self.guard
+    }
+}
+
+impl Deref for TicketWriteGuard<'_> {
+    type Target = Ticket;
+
+    fn deref(&self) -> &Ticket {
+        &self.guard
+    }
+}
+
+impl DerefMut for TicketWriteGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Ticket {
+        &mut self.guard
+    }
+}
+
+impl<B, G> Store<TicketId, Arc<TicketMutex>, B, G>
+where
+    B: Backing<TicketId, Arc<TicketMutex>>,
+    G: IdGenerator<TicketId>,
+{
+    /// Lock the tickets with the given ids, in `TicketId` order whatever the
+    /// order of `ids`: callers that only lock through here can't deadlock.
+    ///
+    /// Duplicate ids are locked once; the guards come back sorted by id.
+    /// Poisoned tickets are recovered as described in [`crate::poison`].
+    /// If a ticket is missing or quarantined, none is left locked.
+    pub fn lock_many(
+        &self,
+        ids: impl IntoIterator<Item = TicketId>,
+    ) -> Result<Vec<TicketMutexGuard<'_>>, TicketLockError> {
+        self.in_order(ids)?
+            .map(|ticket| recover(ticket.id(), ticket.lock(), || ticket.clear_poison()))
+            .collect()
+    }
+}
+
+impl<B, G> Store<TicketId, Arc<TicketRwLock>, B, G>
+where
+    B: Backing<TicketId, Arc<TicketRwLock>>,
+    G: IdGenerator<TicketId>,
+{
+    /// Like [`lock_many`](Store::lock_many), with read locks.
+    pub fn read_many(
+        &self,
+        ids: impl IntoIterator<Item = TicketId>,
+    ) -> Result<Vec<TicketReadGuard<'_>>, TicketLockError> {
+        self.in_order(ids)?
+            .map(|ticket| recover(ticket.id(), ticket.read(), || ticket.clear_poison()))
+            .collect()
+    }
+
+    /// Like [`lock_many`](Store::lock_many), with write locks.
+    pub fn write_many(
+        &self,
+        ids: impl IntoIterator<Item = TicketId>,
+    ) -> Result<Vec<TicketWriteGuard<'_>>, TicketLockError> {
+        self.in_order(ids)?
+            .map(|ticket| recover(ticket.id(), ticket.write(), || ticket.clear_poison()))
+            .collect()
+    }
+}
+
+impl<V, B, G> Store<TicketId, Arc<V>, B, G>
+where
+    B: Backing<TicketId, Arc<V>>,
+    G: IdGenerator<TicketId>,
+{
+    /// The values for `ids`, deduplicated and sorted by id, if they all exist.
+    fn in_order(
+        &self,
+        ids: impl IntoIterator<Item = TicketId>,
+    ) -> Result<impl Iterator<Item = &V>, TicketLockError> {
+        let ids: BTreeSet<TicketId> = ids.into_iter().collect();
+        let values = ids
+            .into_iter()
+            .map(|id| {
+                self.get(id)
+                    .map(Arc::as_ref)
+                    .ok_or(TicketLockError::NotFound(id))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(values.into_iter())
+    }
+}
+
+/// What a lock needs to know to check the order it's taken in.
+#[derive(Debug)]
+struct Tracker {
+    ticket: TicketId,
+    /// Ticket ids are only unique within a store: locks are told apart by this instead.
+    #[cfg(debug_assertions)]
+    lock: u64,
+}
+
+/// Marks the lock as held by the current thread until dropped.
+struct Held<'a>(#[allow(dead_code)] &'a Tracker);
+
+#[cfg(not(debug_assertions))]
+impl Tracker {
+    fn new(ticket: TicketId) -> Self {
+        Self { ticket }
+    }
+
+    fn acquire(&self) -> Held<'_> {
+        Held(self)
+    }
+}
+
+#[cfg(debug_assertions)]
+impl Tracker {
+    fn new(ticket: TicketId) -> Self {
+        Self {
+            ticket,
+            lock: graph::next_lock(),
+        }
+    }
+
+    /// Check that taking the lock now can't deadlock, then record it as held.
+    fn acquire(&self) -> Held<'_> {
+        graph::acquire(self.lock, self.ticket);
+        Held(self)
+    }
+}
+
+#[cfg(debug_assertions)]
+impl Drop for Tracker {
+    fn drop(&mut self) {
+        graph::forget(self.lock);
+    }
+}
+
+#[cfg(debug_assertions)]
+impl Drop for Held<'_> {
+    fn drop(&mut self) {
+        graph::release(self.0.lock);
+    }
+}
+
+/// The order locks have been taken in so far, as a graph: there's an edge from
+/// `a` to `b` if `b` was locked while holding `a`. A cycle is a potential deadlock.
+#[cfg(debug_assertions)]
+mod graph {
+    use crate::data::TicketId;
+    use std::backtrace::Backtrace;
+    use std::cell::RefCell;
+    use std::collections::{HashMap, HashSet};
+    use std::fmt::Write;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, LazyLock, Mutex, PoisonError};
+
+    struct Edge {
+        from: TicketId,
+        to: TicketId,
+        /// Where `to` was first locked while holding `from`.
+        backtrace: Arc<Backtrace>,
+    }
+
+    type Graph = HashMap<u64, HashMap<u64, Edge>>;
+
+    static NEXT_LOCK: AtomicU64 = AtomicU64::new(0);
+    static GRAPH: LazyLock<Mutex<Graph>> = LazyLock::new(Mutex::default);
+
+    thread_local! {
+        /// The locks held by the current thread, in the order they were taken.
+        static HELD: RefCell<Vec<(u64, TicketId)>> = const { RefCell::new(Vec::new()) };
+    }
+
+    pub(super) fn next_lock() -> u64 {
+        NEXT_LOCK.fetch_add(1, Ordering::Relaxed)
+    }
+
+    pub(super) fn acquire(lock: u64, ticket: TicketId) {
+        let held = HELD.with(|held| held.borrow().clone());
+        if held.iter().any(|(other, _)| *other == lock) {
+            panic!(
+                "Ticket #{} is already locked by this thread: locking it again would deadlock",
+                u64::from(ticket)
+            );
+        }
+
+        let mut graph = GRAPH.lock().unwrap_or_else(PoisonError::into_inner);
+        // Only captured if this acquisition adds an edge, i.e. the first time
+        // these two locks are taken together.
+        let mut backtrace = None;
+        for (other, other_ticket) in held {
+            if graph
+                .get(&other)
+                .is_some_and(|edges| edges.contains_key(&lock))
+            {
+                continue;
+            }
+            if let Some(path) = path(&graph, lock, other) {
+                let message = inversion(&graph, ticket, other_ticket, &path);
+                // Don't poison the graph for everyone else.
+                drop(graph);
+                panic!("{message}");
+            }
+            let backtrace = backtrace
+                .get_or_insert_with(|| Arc::new(Backtrace::force_capture()))
+                .clone();
+            let edge = Edge {
+                from: other_ticket,
+                to: ticket,
+                backtrace,
+            };
+            graph.entry(other).or_default().insert(lock, edge);
+        }
+        drop(graph);
+        HELD.with(|held| held.borrow_mut().push((lock, ticket)));
+    }
+
+    pub(super) fn release(lock: u64) {
+        HELD.with(|held| {
+            let mut held = held.borrow_mut();
+            if let Some(i) = held.iter().rposition(|(other, _)| *other == lock) {
+                held.remove(i);
+            }
+        });
+    }
+
+    /// The lock is gone: nothing can be ordered against it anymore.
+    pub(super) fn forget(lock: u64) {
+        let mut graph = GRAPH.lock().unwrap_or_else(PoisonError::into_inner);
+        graph.remove(&lock);
+        graph.retain(|_, edges| {
+            edges.remove(&lock);
+            !edges.is_empty()
+        });
+    }
+
+    /// The locks on a path from `from` to `to`, both included, if there's one.
+    fn path(graph: &Graph, from: u64, to: u64) -> Option<Vec<u64>> {
+        let mut stack = vec![vec![from]];
+        let mut seen = HashSet::from([from]);
+        while let Some(path) = stack.pop() {
+            let last = *path.last().unwrap();
+            if last == to {
+                return Some(path);
+            }
+            for next in graph.get(&last).into_iter().flat_map(HashMap::keys) {
+                if seen.insert(*next) {
+                    let mut longer = path.clone();
+                    longer.push(*next);
+                    stack.push(longer);
+                }
+            }
+        }
+        None
+    }
+
+    fn inversion(graph: &Graph, ticket: TicketId, holding: TicketId, path: &[u64]) -> String {
+        let (ticket, holding) = (u64::from(ticket), u64::from(holding));
+        let mut message = format!(
+            "Potential deadlock: locking ticket #{ticket} while holding ticket #{holding}, \
+             but ticket #{holding} was locked while holding ticket #{ticket} before"
+        );
+        let edges: Vec<&Edge> = path.windows(2).map(|w| &graph[&w[0]][&w[1]]).collect();
+        if edges.len() > 1 {
+            let chain: Vec<String> = std::iter::once(edges[0].from)
+                .chain(edges.iter().map(|edge| edge.to))
+                .map(|id| format!("#{}", u64::from(id)))
+                .collect();
+            let _ = write!(message, " (through {})", chain.join(" -> "));
+        }
+        let _ = write!(
+            message,
+            "\n\nThis acquisition:\n{}",
+            Backtrace::force_capture()
+        );
+        for edge in edges {
+            let _ = write!(
+                message,
+                "\n\nTicket #{} locked while holding ticket #{}:\n{}",
+                u64::from(edge.to),
+                u64::from(edge.from),
+                edge.backtrace
+            );
+        }
+        message
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{Status, Ticket, TicketId};
+    use crate::poison::TicketLockError;
+    use crate::test_helpers::ticket_draft;
+    use crate::{TrackedRwTicketStore, TrackedTicketStore};
+    use std::sync::Arc;
+    use std::thread;
+
+    fn store(n: usize) -> (TrackedTicketStore, Vec<TicketId>) {
+        let mut store = TrackedTicketStore::new();
+        let ids = (0..n).map(|_| store.add_ticket(ticket_draft())).collect();
+        (store, ids)
+    }
+
+    #[test]
+    fn consistent_order_is_fine() {
+        let (store, ids) = store(3);
+        for _ in 0..2 {
+            let _a = store[ids[0]].lock().unwrap();
+            let _b = store[ids[1]].lock().unwrap();
+            let _c = store[ids[2]].lock().unwrap();
+        }
+        // Skipping one is fine too.
+        let _a = store[ids[0]].lock().unwrap();
+        let _c = store[ids[2]].lock().unwrap();
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "locking ticket #0 while holding ticket #1, \
+                               but ticket #1 was locked while holding ticket #0 before")]
+    fn inverted_order_panics() {
+        let (store, ids) = store(2);
+        {
+            let _a = store[ids[0]].lock().unwrap();
+            let _b = store[ids[1]].lock().unwrap();
+        }
+        let _b = store[ids[1]].lock().unwrap();
+        let _a = store[ids[0]].lock().unwrap();
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn longer_cycles_are_detected_across_threads() {
+        let mut store = TrackedRwTicketStore::new();
+        let ids: Vec<_> = (0..3).map(|_| store.add_ticket(ticket_draft())).collect();
+        let store = Arc::new(store);
+        for (a, b) in [(0, 1), (1, 2)] {
+            let store = store.clone();
+            let ids = ids.clone();
+            thread::spawn(move || {
+                let _a = store[ids[a]].write().unwrap();
+                let _b = store[ids[b]].read().unwrap();
+            })
+            .join()
+            .unwrap();
+        }
+
+        let panic = thread::spawn(move || {
+            let _c = store[ids[2]].read().unwrap();
+            let _a = store[ids[0]].write().unwrap();
+        })
+        .join()
+        .unwrap_err();
+        let message = panic.downcast_ref::<String>().unwrap();
+        assert!(message.contains("locking ticket #0 while holding ticket #2"));
+        assert!(message.contains("(through #0 -> #1 -> #2)"));
+        assert!(message.contains("Ticket #2 locked while holding ticket #1"));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Ticket #0 is already locked by this thread")]
+    fn relocking_panics() {
+        let (store, ids) = store(1);
+        let _a = store[ids[0]].lock().unwrap();
+        let _again = store[ids[0]].lock().unwrap();
+    }
+
+    #[test]
+    fn tickets_from_different_stores_are_told_apart() {
+        let (first, ids) = store(2);
+        let (second, _) = store(2);
+        {
+            let _a = first[ids[0]].lock().unwrap();
+            let _b = first[ids[1]].lock().unwrap();
+        }
+        // Same ticket ids, but not the same locks: no inversion there.
+        let _b = second[ids[1]].lock().unwrap();
+        let _a = second[ids[0]].lock().unwrap();
+    }
+
+    #[test]
+    fn lock_many_goes_in_id_order() {
+        let (store, ids) = store(3);
+        let guards = store.lock_many([ids[2], ids[0], ids[2], ids[1]]).unwrap();
+        let locked: Vec<TicketId> = guards.iter().map(|ticket| ticket.id).collect();
+        assert_eq!(locked, ids);
+        drop(guards);
+
+        let missing = TicketId::from(42);
+        assert!(matches!(
+            store.lock_many([ids[0], missing]),
+            Err(TicketLockError::NotFound(id)) if id == missing
+        ));
+        // Nothing was left locked.
+        assert!(store[ids[0]].lock().is_ok());
+    }
+
+    #[test]
+    fn lock_many_does_not_deadlock() {
+        let (store, ids) = store(4);
+        let store = Arc::new(store);
+        let threads: Vec<_> = (0..4)
+            .map(|i| {
+                let store = store.clone();
+                let mut ids = ids.clone();
+                ids.rotate_left(i);
+                thread::spawn(move || {
+                    for _ in 0..100 {
+                        let mut guards = store.lock_many(ids.iter().copied()).unwrap();
+                        for ticket in guards.iter_mut() {
+                            ticket.set_status(Status::InProgress);
+                        }
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn write_many_on_a_rwlock_store() {
+        let mut store = TrackedRwTicketStore::new();
+        let ids: Vec<_> = (0..2).map(|_| store.add_ticket(ticket_draft())).collect();
+        let tickets: Vec<Ticket> = store
+            .write_many(ids.iter().rev().copied())
+            .unwrap()
+            .iter()
+            .map(|ticket| (**ticket).clone())
+            .collect();
+        assert_eq!(tickets[0].id, ids[0]);
+        assert_eq!(store.read_many(ids.clone()).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,257 @@
+use crate::data::{Ticket, TicketId};
+use crate::id::IdGenerator;
+use crate::store::Store;
+use std::collections::btree_map::Range;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::num::NonZeroUsize;
+use std::thread;
+
+/// How many threads a bulk operation may use.
+///
+/// Spawning threads isn't free: a store is only split if each thread gets
+/// at least `min_per_thread` tickets. Smaller stores are processed serially.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Parallelism {
+    threads: usize,
+    min_per_thread: usize,
+}
+
+impl Parallelism {
+    pub const DEFAULT_MIN_PER_THREAD: usize = 1024;
+
+    /// Use up to `threads` threads. Zero is taken as one.
+    pub fn new(threads: usize) -> Self {
+        Self {
+            threads: threads.max(1),
+            min_per_thread: Self::DEFAULT_MIN_PER_THREAD,
+        }
+    }
+
+    pub fn serial() -> Self {
+        Self::new(1)
+    }
+
+    pub fn with_min_per_thread(self, min_per_thread: usize) -> Self {
+        Self {
+            min_per_thread: min_per_thread.max(1),
+            ..self
+        }
+    }
+
+    /// How many threads to use for `len` tickets.
+    pub fn threads_for(&self, len: usize) -> usize {
+        self.threads.min(len / self.min_per_thread).max(1)
+    }
+}
+
+impl Default for Parallelism {
+    /// As many threads as the machine can run in parallel.
+    fn default() -> Self {
+        Self::new(thread::available_parallelism().map_or(1, NonZeroUsize::get))
+    }
+}
+
+/// Bulk operations that split the tickets into ranges of ids, each processed
+/// on a scoped thread. Results always come back in id order.
+impl<G> Store<TicketId, Ticket, BTreeMap<TicketId, Ticket>, G>
+where
+    G: IdGenerator<TicketId>,
+{
+    pub fn par_map_tickets<R, F>(&self, parallelism: Parallelism, f: F) -> Vec<R>
+    where
+        R: Send,
+        F: Fn(&Ticket) -> R + Sync,
+    {
+        self.par_ranges(parallelism, |tickets| {
+            tickets.map(|(_, ticket)| f(ticket)).collect::<Vec<_>>()
+        })
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    pub fn par_filter<F>(&self, parallelism: Parallelism, f: F) -> Vec<&Ticket>
+    where
+        F: Fn(&Ticket) -> bool + Sync,
+    {
+        self.par_ranges(parallelism, |tickets| {
+            tickets
+                .map(|(_, ticket)| ticket)
+                .filter(|ticket| f(ticket))
+                .collect::<Vec<_>>()
+        })
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    /// Rebuild an index of the tickets by `key`, e.g. by status.
+    /// The ids under each key are sorted.
+    pub fn par_index_by<K, F>(&self, parallelism: Parallelism, key: F) -> HashMap<K, Vec<TicketId>>
+    where
+        K: Eq + Hash + Send,
+        F: Fn(&Ticket) -> K + Sync,
+    {
+        let partial = self.par_ranges(parallelism, |tickets| {
+            let mut index: HashMap<K, Vec<TicketId>> = HashMap::new();
+            for (id, ticket) in tickets {
+                index.entry(key(ticket)).or_default().push(*id);
+            }
+            index
+        });
+        // The ranges are in id order: appending keeps every list sorted.
+        let mut index: HashMap<K, Vec<TicketId>> = HashMap::new();
+        for part in partial {
+            for (key, ids) in part {
+                index.entry(key).or_default().extend(ids);
+            }
+        }
+        index
+    }
+
+    pub fn par_for_each_mut<F>(&mut self, parallelism: Parallelism, f: F)
+    where
+        F: Fn(&mut Ticket) + Sync,
+    {
+        let threads = parallelism.threads_for(self.len());
+        if threads == 1 {
+            self.items.values_mut().for_each(f);
+            return;
+        }
+        // Mutable ranges of a `BTreeMap` can't be borrowed side by side:
+        // split the references instead.
+        let mut tickets: Vec<&mut Ticket> = self.items.values_mut().collect();
+        let chunk = tickets.len().div_ceil(threads);
+        let f = &f;
+        thread::scope(|scope| {
+            for chunk in tickets.chunks_mut(chunk) {
+                scope.spawn(move || chunk.iter_mut().for_each(|ticket| f(ticket)));
+            }
+        });
+    }
+
+    /// Run `f` on contiguous ranges of tickets, one per thread.
+    /// A panic in `f` is propagated once every thread is done.
+    fn par_ranges<'a, R, F>(&'a self, parallelism: Parallelism, f: F) -> Vec<R>
+    where
+        R: Send,
+        F: Fn(Range<'a, TicketId, Ticket>) -> R + Sync,
+    {
+        let threads = parallelism.threads_for(self.len());
+        if threads == 1 {
+            return vec![f(self.items.range(..))];
+        }
+        let chunk = self.len().div_ceil(threads);
+        let starts: Vec<TicketId> = self.items.keys().step_by(chunk).copied().collect();
+        let f = &f;
+        thread::scope(|scope| {
+            let handles: Vec<_> = starts
+                .iter()
+                .enumerate()
+                .map(|(i, start)| {
+                    let range = match starts.get(i + 1) {
+                        Some(end) => self.items.range(*start..*end),
+                        None => self.items.range(*start..),
+                    };
+                    scope.spawn(move || f(range))
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| {
+                    handle
+                        .join()
+                        .unwrap_or_else(|e| std::panic::resume_unwind(e))
+                })
+                .collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::Status;
+    use crate::test_helpers::ticket_draft;
+    use crate::TicketStore;
+
+    fn store(n: usize) -> TicketStore {
+        let mut store = TicketStore::new();
+        for i in 0..n {
+            let id = store.add_ticket(ticket_draft());
+            store[id].set_status(Status::ALL[i % 3]);
+        }
+        // Leave some gaps in the ids.
+        store.retain(|ticket| u64::from(ticket.id) % 7 != 0);
+        store
+    }
+
+    fn parallel() -> Parallelism {
+        Parallelism::new(4).with_min_per_thread(10)
+    }
+
+    #[test]
+    fn threads_for() {
+        let parallelism = Parallelism::new(4).with_min_per_thread(100);
+        assert_eq!(parallelism.threads_for(0), 1);
+        assert_eq!(parallelism.threads_for(150), 1);
+        assert_eq!(parallelism.threads_for(250), 2);
+        assert_eq!(parallelism.threads_for(10_000), 4);
+        assert_eq!(Parallelism::serial().threads_for(10_000), 1);
+        assert_eq!(Parallelism::new(0), Parallelism::serial());
+    }
+
+    #[test]
+    fn same_results_as_serial() {
+        let store = store(1000);
+        for parallelism in [Parallelism::serial(), parallel()] {
+            let ids = store.par_map_tickets(parallelism, |ticket| ticket.id);
+            let expected: Vec<TicketId> = store.iter().map(|ticket| ticket.id).collect();
+            assert_eq!(ids, expected);
+
+            let done = store.par_filter(parallelism, |ticket| ticket.status == Status::Done);
+            let expected: Vec<&Ticket> = store
+                .iter()
+                .filter(|ticket| ticket.status == Status::Done)
+                .collect();
+            assert_eq!(done, expected);
+
+            let index = store.par_index_by(parallelism, |ticket| ticket.status);
+            assert_eq!(index.values().map(Vec::len).sum::<usize>(), store.len());
+            for (status, ids) in index {
+                assert!(ids.is_sorted());
+                assert!(ids.iter().all(|id| store[*id].status == status));
+            }
+        }
+    }
+
+    #[test]
+    fn empty_and_tiny_stores() {
+        let empty = store(0);
+        assert!(empty
+            .par_map_tickets(parallel(), |ticket| ticket.id)
+            .is_empty());
+        let tiny = store(3);
+        assert_eq!(tiny.par_filter(parallel(), |_| true).len(), 2);
+    }
+
+    #[test]
+    fn for_each_mut() {
+        let mut store = store(1000);
+        store.par_for_each_mut(parallel(), |ticket| ticket.set_status(Status::Done));
+        assert!(store.iter().all(|ticket| ticket.status == Status::Done));
+        assert!(store.iter().all(|ticket| ticket.resolved_at.is_some()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Oops")]
+    fn panics_are_propagated() {
+        let store = store(1000);
+        store.par_map_tickets(parallel(), |ticket| {
+            if u64::from(ticket.id) == 500 {
+                panic!("Oops");
+            }
+        });
+    }
+}
@@ -0,0 +1,260 @@
+//! Tickets whose lock was poisoned by a panicking holder.
+//!
+//! A panic while holding a ticket leaves it wherever the holder got it to,
+//! possibly halfway through an update. Rather than making every caller deal
+//! with `PoisonError`, the stores check the ticket against the invariants they
+//! own: if they hold, the poison is cleared and the ticket is handed out as usual.
+//! If they don't, the ticket is quarantined: every access fails with
+//! [`TicketLockError::Quarantined`] until it's [repaired](Store::repair) or removed.
+//!
+//! The status and resolution date are public fields, which callers are free to
+//! set as they see fit: they aren't checked against each other.
+use crate::backing::Backing;
+use crate::data::{Ticket, TicketId};
+use crate::id::IdGenerator;
+use crate::store::Store;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, LockResult, Mutex, MutexGuard, PoisonError, RwLock};
+use std::sync::{RwLockReadGuard, RwLockWriteGuard};
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TicketLockError {
+    #[error("There is no ticket with id {0:?}")]
+    NotFound(TicketId),
+    #[error("Ticket {id:?} is quarantined: {violation}")]
+    Quarantined { id: TicketId, violation: Violation },
+}
+
+/// Why a ticket left behind by a panic can't be trusted.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum Violation {
+    #[error("It's stored under id {expected:?}, but says it's {found:?}")]
+    WrongId { expected: TicketId, found: TicketId },
+}
+
+/// Check `ticket`, stored under `id`, against the invariants the store upholds.
+///
+/// Titles and descriptions need no checking: their types can only hold valid ones.
+pub fn validate(id: TicketId, ticket: &Ticket) -> Result<(), Violation> {
+    if ticket.id != id {
+        return Err(Violation::WrongId {
+            expected: id,
+            found: ticket.id,
+        });
+    }
+    Ok(())
+}
+
+/// Hand out the guard if the lock isn't poisoned, or if the ticket is still valid,
+/// in which case `clear_poison` is called.
+pub(crate) fn recover<G: Deref<Target = Ticket>>(
+    id: TicketId,
+    result: LockResult<G>,
+    clear_poison: impl FnOnce(),
+) -> Result<G, TicketLockError> {
+    let guard = match result {
+        Ok(guard) => return Ok(guard),
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    match validate(id, &guard) {
+        Ok(()) => {
+            clear_poison();
+            Ok(guard)
+        }
+        Err(violation) => Err(TicketLockError::Quarantined { id, violation }),
+    }
+}
+
+/// Run `f` on the ticket, then let it out of quarantine if it's valid.
+fn repair<G: DerefMut<Target = Ticket>>(
+    id: TicketId,
+    mut ticket: G,
+    f: impl FnOnce(&mut Ticket),
+    clear_poison: impl FnOnce(),
+) -> Result<(), TicketLockError> {
+    f(&mut ticket);
+    validate(id, &ticket).map_err(|violation| TicketLockError::Quarantined { id, violation })?;
+    clear_poison();
+    Ok(())
+}
+
+impl<B, G> Store<TicketId, Arc<Mutex<Ticket>>, B, G>
+where
+    B: Backing<TicketId, Arc<Mutex<Ticket>>>,
+    G: IdGenerator<TicketId>,
+{
+    /// Lock a ticket, recovering it if a previous holder panicked.
+    pub fn lock(&self, id: TicketId) -> Result<MutexGuard<'_, Ticket>, TicketLockError> {
+        let ticket = self.get(id).ok_or(TicketLockError::NotFound(id))?;
+        recover(id, ticket.lock(), || ticket.clear_poison())
+    }
+
+    /// Fix a ticket up with `f`: if it's valid afterwards, it's no longer quarantined.
+    pub fn repair(&self, id: TicketId, f: impl FnOnce(&mut Ticket)) -> Result<(), TicketLockError> {
+        let ticket = self.get(id).ok_or(TicketLockError::NotFound(id))?;
+        let guard = ticket.lock().unwrap_or_else(PoisonError::into_inner);
+        repair(id, guard, f, || ticket.clear_poison())
+    }
+
+    /// The ids of the tickets in quarantine.
+    ///
+    /// Poisoned tickets have to be locked to be checked: this blocks while
+    /// one of them is held.
+    pub fn quarantined(&self) -> Vec<TicketId> {
+        self.items
+            .iter()
+            .filter(|(_, ticket)| ticket.is_poisoned())
+            .filter_map(|(id, ticket)| {
+                let ticket = ticket.lock().unwrap_or_else(PoisonError::into_inner);
+                validate(*id, &ticket).is_err().then_some(*id)
+            })
+            .collect()
+    }
+}
+
+impl<B, G> Store<TicketId, Arc<RwLock<Ticket>>, B, G>
+where
+    B: Backing<TicketId, Arc<RwLock<Ticket>>>,
+    G: IdGenerator<TicketId>,
+{
+    /// Read-lock a ticket, recovering it if a previous writer panicked.
+    pub fn read(&self, id: TicketId) -> Result<RwLockReadGuard<'_, Ticket>, TicketLockError> {
+        let ticket = self.get(id).ok_or(TicketLockError::NotFound(id))?;
+        recover(id, ticket.read(), || ticket.clear_poison())
+    }
+
+    /// Write-lock a ticket, recovering it if a previous writer panicked.
+    pub fn write(&self, id: TicketId) -> Result<RwLockWriteGuard<'_, Ticket>, TicketLockError> {
+        let ticket = self.get(id).ok_or(TicketLockError::NotFound(id))?;
+        recover(id, ticket.write(), || ticket.clear_poison())
+    }
+
+    /// See [`repair`](Store::repair) on the `Mutex` store.
+    pub fn repair(&self, id: TicketId, f: impl FnOnce(&mut Ticket)) -> Result<(), TicketLockError> {
+        let ticket = self.get(id).ok_or(TicketLockError::NotFound(id))?;
+        let guard = ticket.write().unwrap_or_else(PoisonError::into_inner);
+        repair(id, guard, f, || ticket.clear_poison())
+    }
+
+    /// See [`quarantined`](Store::quarantined) on the `Mutex` store.
+    pub fn quarantined(&self) -> Vec<TicketId> {
+        self.items
+            .iter()
+            .filter(|(_, ticket)| ticket.is_poisoned())
+            .filter_map(|(id, ticket)| {
+                let ticket = ticket.read().unwrap_or_else(PoisonError::into_inner);
+                validate(*id, &ticket).is_err().then_some(*id)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::Status;
+    use crate::store::FromDraft;
+    use crate::test_helpers::ticket_draft;
+    use crate::{LockedTicketStore, RwLockedTicketStore};
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    /// Run `f` on the ticket, then panic while still holding it.
+    fn poison(store: &LockedTicketStore, id: TicketId, f: impl FnOnce(&mut Ticket)) {
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            let mut ticket = store[id].lock().unwrap();
+            f(&mut ticket);
+            panic!("Oops");
+        }));
+        assert!(result.is_err());
+        assert!(store[id].is_poisoned());
+    }
+
+    #[test]
+    fn a_valid_ticket_is_recovered() {
+        let mut store = LockedTicketStore::new();
+        let id = store.add_ticket(ticket_draft());
+        poison(&store, id, |ticket| ticket.set_status(Status::Done));
+
+        assert_eq!(store.lock(id).unwrap().status, Status::Done);
+        assert!(!store[id].is_poisoned());
+        assert!(store.quarantined().is_empty());
+    }
+
+    #[test]
+    fn fields_set_by_hand_are_recovered() {
+        let mut store = LockedTicketStore::new();
+        let id = store.add_ticket(ticket_draft());
+        poison(&store, id, |ticket| ticket.status = Status::Done);
+
+        let ticket = store.lock(id).unwrap();
+        assert_eq!((ticket.status, ticket.resolved_at), (Status::Done, None));
+    }
+
+    #[test]
+    fn an_invalid_ticket_is_quarantined_until_repaired() {
+        let mut store = LockedTicketStore::new();
+        let id = store.add_ticket(ticket_draft());
+        let other = store.add_ticket(ticket_draft());
+        poison(&store, id, |ticket| ticket.id = TicketId::from(7));
+
+        let quarantined = TicketLockError::Quarantined {
+            id,
+            violation: Violation::WrongId {
+                expected: id,
+                found: TicketId::from(7),
+            },
+        };
+        assert_eq!(store.lock(id).unwrap_err(), quarantined);
+        // Still there on the next attempt.
+        assert_eq!(store.lock(id).unwrap_err(), quarantined);
+        assert_eq!(store.quarantined(), [id]);
+        // The others aren't affected.
+        assert!(store.lock(other).is_ok());
+
+        // A repair that doesn't fix it keeps it in quarantine.
+        assert_eq!(store.repair(id, |_| {}).unwrap_err(), quarantined);
+        store.repair(id, |ticket| ticket.id = id).unwrap();
+        assert_eq!(store.lock(id).unwrap().id, id);
+        assert!(store.quarantined().is_empty());
+    }
+
+    #[test]
+    fn a_ticket_stored_under_another_id_is_quarantined() {
+        let mut store = LockedTicketStore::new();
+        let valid = store.add_ticket(ticket_draft());
+        let misplaced = store.insert_with(|id| {
+            let ticket = Ticket::from_draft(TicketId::from(u64::from(id) + 1), ticket_draft());
+            Arc::new(Mutex::new(ticket))
+        });
+        poison(&store, valid, |_| {});
+        poison(&store, misplaced, |_| {});
+
+        assert_eq!(store.quarantined(), [misplaced]);
+    }
+
+    #[test]
+    fn rwlock_store() {
+        let mut store = RwLockedTicketStore::new();
+        let id = store.add_ticket(ticket_draft());
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            let mut ticket = store.write(id).unwrap();
+            ticket.id = TicketId::from(7);
+            panic!("Oops");
+        }));
+        assert!(result.is_err());
+
+        assert!(matches!(
+            store.read(id),
+            Err(TicketLockError::Quarantined { .. })
+        ));
+        assert_eq!(store.quarantined(), [id]);
+        store.repair(id, |ticket| ticket.id = id).unwrap();
+        assert!(store.write(id).is_ok());
+
+        let missing = TicketId::from(42);
+        assert!(matches!(
+            store.read(missing),
+            Err(TicketLockError::NotFound(id)) if id == missing
+        ));
+    }
+}
@@ -0,0 +1,271 @@
+//! Summaries of the tickets in a store, e.g. for a weekly standup.
+//!
+//! ```text
+//! let report = Report::new(&store, SystemTime::now(), &[Duration::from_secs(7 * 24 * 3600)]);
+//! println!("{report}");
+//! ```
+use crate::data::{Status, Ticket};
+use serde::Serialize;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Report {
+    /// Seconds since the Unix epoch.
+    pub generated_at: u64,
+    pub counts: StatusCounts,
+    pub throughput: Vec<Throughput>,
+    /// Tickets that aren't done yet, oldest first.
+    pub open: Vec<OpenTicket>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct StatusCounts {
+    pub to_do: usize,
+    pub in_progress: usize,
+    pub done: usize,
+}
+
+/// How many tickets were opened and resolved in the last `window_secs` seconds.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Throughput {
+    pub window_secs: u64,
+    pub opened: usize,
+    pub resolved: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct OpenTicket {
+    pub id: u64,
+    pub status: String,
+    pub age_secs: u64,
+}
+
+impl StatusCounts {
+    pub fn get(&self, status: Status) -> usize {
+        match status {
+            Status::ToDo => self.to_do,
+            Status::InProgress => self.in_progress,
+            Status::Done => self.done,
+        }
+    }
+
+    fn bump(&mut self, status: Status) {
+        match status {
+            Status::ToDo => self.to_do += 1,
+            Status::InProgress => self.in_progress += 1,
+            Status::Done => self.done += 1,
+        }
+    }
+}
+
+impl Report {
+    /// Summarise `tickets` as seen at `now`, with one throughput row per window.
+    pub fn new<'a, I>(tickets: I, now: SystemTime, windows: &[Duration]) -> Self
+    where
+        I: IntoIterator<Item = &'a Ticket>,
+    {
+        let mut counts = StatusCounts::default();
+        let mut throughput: Vec<Throughput> = windows
+            .iter()
+            .map(|window| Throughput {
+                window_secs: window.as_secs(),
+                opened: 0,
+                resolved: 0,
+            })
+            .collect();
+        let mut open = Vec::new();
+
+        for ticket in tickets {
+            counts.bump(ticket.status);
+
+            for (row, window) in throughput.iter_mut().zip(windows) {
+                if age(ticket.created_at, now) <= *window {
+                    row.opened += 1;
+                }
+                if let Some(resolved_at) = ticket.resolved_at {
+                    if age(resolved_at, now) <= *window {
+                        row.resolved += 1;
+                    }
+                }
+            }
+
+            if ticket.status != Status::Done {
+                open.push((ticket.id, ticket.status, age(ticket.created_at, now)));
+            }
+        }
+
+        open.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(&b.0)));
+
+        Report {
+            generated_at: seconds_since_epoch(now),
+            counts,
+            throughput,
+            open: open
+                .into_iter()
+                .map(|(id, status, age)| OpenTicket {
+                    id: id.into(),
+                    status: status.to_string(),
+                    age_secs: age.as_secs(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("A report is always serializable")
+    }
+
+    /// The plain-text rendering, same as `Display`.
+    pub fn to_table(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<12} {:>6}", "Status", "Count")?;
+        for status in Status::ALL {
+            writeln!(f, "{:<12} {:>6}", status, self.counts.get(status))?;
+        }
+
+        if !self.throughput.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "{:<12} {:>6} {:>8}", "Window", "Opened", "Resolved")?;
+            for row in &self.throughput {
+                writeln!(
+                    f,
+                    "{:<12} {:>6} {:>8}",
+                    human_duration(row.window_secs),
+                    row.opened,
+                    row.resolved
+                )?;
+            }
+        }
+
+        if !self.open.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "{:<8} {:<12} {:>8}", "Open", "Status", "Age")?;
+            for ticket in &self.open {
+                writeln!(
+                    f,
+                    "{:<8} {:<12} {:>8}",
+                    format!("#{}", ticket.id),
+                    ticket.status,
+                    human_duration(ticket.age_secs)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Timestamps in the future (clock skew) count as age zero.
+fn age(since: SystemTime, now: SystemTime) -> Duration {
+    now.duration_since(since).unwrap_or_default()
+}
+
+fn seconds_since_epoch(at: SystemTime) -> u64 {
+    at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+fn human_duration(secs: u64) -> String {
+    const MINUTE: u64 = 60;
+    const HOUR: u64 = 60 * MINUTE;
+    const DAY: u64 = 24 * HOUR;
+
+    if secs >= DAY {
+        format!("{}d {}h", secs / DAY, secs % DAY / HOUR)
+    } else if secs >= HOUR {
+        format!("{}h {}m", secs / HOUR, secs % HOUR / MINUTE)
+    } else if secs >= MINUTE {
+        format!("{}m", secs / MINUTE)
+    } else {
+        format!("{secs}s")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::TicketId;
+    use crate::test_helpers::ticket_draft;
+    use crate::TicketStore;
+
+    const DAY: Duration = Duration::from_secs(24 * 3600);
+
+    /// Tickets created 10, 5 and 1 day(s) before `now`; the 5-day-old one was resolved 2 days ago.
+    fn fixture(now: SystemTime) -> TicketStore {
+        let mut store: TicketStore = (0..3).map(|_| ticket_draft()).collect();
+        for (ticket, days) in store.iter_mut().zip([10, 5, 1]) {
+            ticket.created_at = now - DAY * days;
+        }
+        store[TicketId::from(0)].set_status(Status::InProgress);
+        store[TicketId::from(1)].set_status_at(Status::Done, now - DAY * 2);
+        store
+    }
+
+    #[test]
+    fn counts_throughput_and_ages() {
+        let now = UNIX_EPOCH + DAY * 1000;
+        let store = fixture(now);
+        let report = Report::new(&store, now, &[DAY * 3, DAY * 7, DAY * 30]);
+
+        assert_eq!(
+            report.counts,
+            StatusCounts {
+                to_do: 1,
+                in_progress: 1,
+                done: 1
+            }
+        );
+        let rows: Vec<(usize, usize)> = report
+            .throughput
+            .iter()
+            .map(|row| (row.opened, row.resolved))
+            .collect();
+        assert_eq!(rows, vec![(1, 1), (2, 1), (3, 1)]);
+
+        let open: Vec<(u64, u64)> = report.open.iter().map(|t| (t.id, t.age_secs)).collect();
+        assert_eq!(open, vec![(0, 10 * DAY.as_secs()), (2, DAY.as_secs())]);
+    }
+
+    #[test]
+    fn table() {
+        let now = UNIX_EPOCH + DAY * 1000;
+        let report = Report::new(&fixture(now), now, &[DAY * 7]);
+        let expected = "\
+Status        Count
+To-Do             1
+In Progress       1
+Done              1
+
+Window       Opened Resolved
+7d 0h             2        1
+
+Open     Status            Age
+#0       In Progress    10d 0h
+#2       To-Do           1d 0h
+";
+        assert_eq!(report.to_table(), expected);
+    }
+
+    #[test]
+    fn json() {
+        let now = UNIX_EPOCH + DAY * 1000;
+        let report = Report::new(&fixture(now), now, &[DAY]);
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["generated_at"], 1000 * DAY.as_secs());
+        assert_eq!(json["counts"]["in_progress"], 1);
+        assert_eq!(json["throughput"][0]["opened"], 1);
+        assert_eq!(json["open"][1]["status"], "To-Do");
+    }
+
+    #[test]
+    fn reopening_clears_resolution() {
+        let now = UNIX_EPOCH + DAY * 1000;
+        let mut store = fixture(now);
+        store[TicketId::from(1)].set_status(Status::ToDo);
+        assert_eq!(store[TicketId::from(1)].resolved_at, None);
+    }
+}
@@ -0,0 +1,145 @@
+use crate::data::{Ticket, TicketDraft, TicketId};
+use crate::store::FromDraft;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+
+/// A ticket store that can be shared across threads as-is (e.g. behind an `Arc`).
+///
+/// Tickets are spread over independent shards by the hash of their id, so
+/// operations on tickets living in different shards never wait on each other.
+/// Ids come from an atomic counter: allocating one never takes a lock.
+pub struct ShardedTicketStore {
+    shards: Box<[RwLock<BTreeMap<TicketId, Ticket>>]>,
+    counter: AtomicU64,
+}
+
+impl ShardedTicketStore {
+    pub const DEFAULT_SHARDS: usize = 16;
+
+    pub fn new() -> Self {
+        Self::with_shards(Self::DEFAULT_SHARDS)
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `n_shards` is zero.
+    pub fn with_shards(n_shards: usize) -> Self {
+        assert!(n_shards > 0, "A sharded store needs at least one shard");
+        Self {
+            shards: (0..n_shards).map(|_| RwLock::default()).collect(),
+            counter: AtomicU64::new(0),
+        }
+    }
+
+    pub fn add_ticket(&self, draft: TicketDraft) -> TicketId {
+        let id = TicketId::from(self.counter.fetch_add(1, Ordering::Relaxed));
+        let ticket = Ticket::from_draft(id, draft);
+        self.shard(id).write().unwrap().insert(id, ticket);
+        id
+    }
+
+    pub fn get(&self, id: TicketId) -> Option<Ticket> {
+        self.shard(id).read().unwrap().get(&id).cloned()
+    }
+
+    /// Run `f` on the ticket while holding its shard's write lock.
+    pub fn update<R>(&self, id: TicketId, f: impl FnOnce(&mut Ticket) -> R) -> Option<R> {
+        self.shard(id).write().unwrap().get_mut(&id).map(f)
+    }
+
+    pub fn remove(&self, id: TicketId) -> Option<Ticket> {
+        self.shard(id).write().unwrap().remove(&id)
+    }
+
+    /// How many ids have been handed out so far. Doesn't take any lock.
+    pub fn issued(&self) -> u64 {
+        self.counter.load(Ordering::Relaxed)
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|s| s.read().unwrap().len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// A copy of every ticket, ordered by id.
+    ///
+    /// Shards are visited one at a time: writes to a shard that was already
+    /// visited won't show up.
+    pub fn tickets(&self) -> Vec<Ticket> {
+        let mut tickets: Vec<Ticket> = self
+            .shards
+            .iter()
+            .flat_map(|s| s.read().unwrap().values().cloned().collect::<Vec<_>>())
+            .collect();
+        tickets.sort_by_key(|t| t.id);
+        tickets
+    }
+
+    fn shard(&self, id: TicketId) -> &RwLock<BTreeMap<TicketId, Ticket>> {
+        let mut hasher = DefaultHasher::new();
+        id.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % self.shards.len()]
+    }
+}
+
+impl Default for ShardedTicketStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::Status;
+    use crate::test_helpers::ticket_draft;
+
+    #[test]
+    fn concurrent_inserts_get_unique_ids() {
+        let store = ShardedTicketStore::with_shards(4);
+        let ids: Vec<TicketId> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..8)
+                .map(|_| {
+                    scope.spawn(|| {
+                        (0..100)
+                            .map(|_| store.add_ticket(ticket_draft()))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|h| h.join().unwrap())
+                .collect()
+        });
+
+        assert_eq!(store.issued(), 800);
+        assert_eq!(store.len(), 800);
+        let mut unique = ids.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), ids.len());
+
+        let ordered: Vec<TicketId> = store.tickets().into_iter().map(|t| t.id).collect();
+        assert_eq!(ordered, unique);
+    }
+
+    #[test]
+    fn update_and_remove() {
+        let store = ShardedTicketStore::new();
+        let id = store.add_ticket(ticket_draft());
+        store.update(id, |t| t.set_status(Status::Done)).unwrap();
+        assert_eq!(store.get(id).unwrap().status, Status::Done);
+
+        assert!(store.remove(id).is_some());
+        assert!(store.get(id).is_none());
+        assert!(store.update(id, |_| ()).is_none());
+        assert!(store.is_empty());
+    }
+}
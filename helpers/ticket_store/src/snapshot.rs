@@ -0,0 +1,224 @@
+use crate::data::{Ticket, TicketDraft, TicketId};
+use crate::store::FromDraft;
+use arc_swap::ArcSwap;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// A ticket store where readers never block and never wait for writers.
+///
+/// The whole store is an immutable [`Snapshot`]. A writer copies the current
+/// snapshot, applies its changes to the copy and publishes it atomically.
+/// Readers holding an older snapshot keep seeing it, unchanged, until they drop
+/// it; the memory is freed when the last reader lets go.
+///
+/// Copies are shallow: tickets are shared between snapshots through `Arc`,
+/// only the index is duplicated on every write.
+pub struct SnapshotTicketStore {
+    current: ArcSwap<State>,
+    /// Serializes writers, so that no write is lost to a concurrent one.
+    writer: Mutex<()>,
+}
+
+#[derive(Clone, Default)]
+struct State {
+    version: u64,
+    counter: u64,
+    tickets: BTreeMap<TicketId, Arc<Ticket>>,
+}
+
+/// A consistent, point-in-time view of every ticket in a [`SnapshotTicketStore`].
+#[derive(Clone)]
+pub struct Snapshot(Arc<State>);
+
+/// The changes a writer makes in [`SnapshotTicketStore::write`].
+pub struct Writer<'a> {
+    state: &'a mut State,
+}
+
+impl SnapshotTicketStore {
+    pub fn new() -> Self {
+        Self {
+            current: ArcSwap::from_pointee(State::default()),
+            writer: Mutex::new(()),
+        }
+    }
+
+    /// The latest published snapshot. Doesn't take any lock.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot(self.current.load_full())
+    }
+
+    pub fn get(&self, id: TicketId) -> Option<Arc<Ticket>> {
+        self.current.load().tickets.get(&id).cloned()
+    }
+
+    /// Apply all the changes made by `f` at once: readers see either none or all of them.
+    pub fn write<R>(&self, f: impl FnOnce(&mut Writer<'_>) -> R) -> R {
+        // The shared state is only replaced after `f` returns, so a panicking
+        // writer can't leave anything half-done behind: the poison can be ignored.
+        let _guard = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut next = State::clone(&self.current.load());
+        let outcome = f(&mut Writer { state: &mut next });
+        next.version += 1;
+        self.current.store(Arc::new(next));
+        outcome
+    }
+
+    pub fn add_ticket(&self, draft: TicketDraft) -> TicketId {
+        self.write(|w| w.add_ticket(draft))
+    }
+
+    pub fn update<R>(&self, id: TicketId, f: impl FnOnce(&mut Ticket) -> R) -> Option<R> {
+        self.write(|w| w.get_mut(id).map(f))
+    }
+
+    pub fn remove(&self, id: TicketId) -> Option<Arc<Ticket>> {
+        self.write(|w| w.remove(id))
+    }
+}
+
+impl Default for SnapshotTicketStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Snapshot {
+    /// Increases by one with every published write.
+    pub fn version(&self) -> u64 {
+        self.0.version
+    }
+
+    pub fn get(&self, id: TicketId) -> Option<&Ticket> {
+        self.0.tickets.get(&id).map(|t| &**t)
+    }
+
+    /// The tickets, ordered by id.
+    pub fn iter(&self) -> impl Iterator<Item = &Ticket> {
+        self.0.tickets.values().map(|t| &**t)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.tickets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.tickets.is_empty()
+    }
+}
+
+impl Writer<'_> {
+    pub fn add_ticket(&mut self, draft: TicketDraft) -> TicketId {
+        let id = TicketId::from(self.state.counter);
+        self.state.counter += 1;
+        let ticket = Ticket::from_draft(id, draft);
+        self.state.tickets.insert(id, Arc::new(ticket));
+        id
+    }
+
+    pub fn get(&self, id: TicketId) -> Option<&Ticket> {
+        self.state.tickets.get(&id).map(|t| &**t)
+    }
+
+    /// Tickets still shared with published snapshots are copied before being handed out.
+    pub fn get_mut(&mut self, id: TicketId) -> Option<&mut Ticket> {
+        self.state.tickets.get_mut(&id).map(Arc::make_mut)
+    }
+
+    pub fn remove(&mut self, id: TicketId) -> Option<Arc<Ticket>> {
+        self.state.tickets.remove(&id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::Status;
+    use crate::test_helpers::ticket_draft;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[test]
+    fn snapshots_are_isolated_from_later_writes() {
+        let store = SnapshotTicketStore::new();
+        let id = store.add_ticket(ticket_draft());
+        let before = store.snapshot();
+
+        store.update(id, |t| t.set_status(Status::Done)).unwrap();
+        store.add_ticket(ticket_draft());
+
+        assert_eq!(before.get(id).unwrap().status, Status::ToDo);
+        assert_eq!(before.len(), 1);
+
+        let after = store.snapshot();
+        assert_eq!(after.get(id).unwrap().status, Status::Done);
+        assert_eq!(after.len(), 2);
+        assert_eq!(after.version(), before.version() + 2);
+    }
+
+    #[test]
+    fn readers_never_see_a_partial_write() {
+        let store = SnapshotTicketStore::new();
+        let (a, b) = store.write(|w| (w.add_ticket(ticket_draft()), w.add_ticket(ticket_draft())));
+        let done = AtomicBool::new(false);
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    while !done.load(Ordering::Relaxed) {
+                        let snapshot = store.snapshot();
+                        assert_eq!(
+                            snapshot.get(a).unwrap().status,
+                            snapshot.get(b).unwrap().status
+                        );
+                    }
+                });
+            }
+
+            for status in [Status::InProgress, Status::Done]
+                .into_iter()
+                .cycle()
+                .take(500)
+            {
+                store.write(|w| {
+                    w.get_mut(a).unwrap().status = status;
+                    w.get_mut(b).unwrap().status = status;
+                });
+            }
+            done.store(true, Ordering::Relaxed);
+        });
+    }
+
+    #[test]
+    fn old_snapshots_are_reclaimed() {
+        let store = SnapshotTicketStore::new();
+        let id = store.add_ticket(ticket_draft());
+        let ticket = store.get(id).unwrap();
+        let old = store.snapshot();
+
+        store.update(id, |t| t.status = Status::Done);
+        // Held by `ticket` and by `old`.
+        assert_eq!(Arc::strong_count(&ticket), 2);
+
+        drop(old);
+        assert_eq!(Arc::strong_count(&ticket), 1);
+    }
+
+    #[test]
+    fn a_panicking_writer_publishes_nothing() {
+        let store = SnapshotTicketStore::new();
+        let id = store.add_ticket(ticket_draft());
+
+        let outcome = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            store.write(|w| {
+                w.get_mut(id).unwrap().status = Status::Done;
+                panic!("Oops");
+            })
+        }));
+        assert!(outcome.is_err());
+        assert_eq!(store.get(id).unwrap().status, Status::ToDo);
+
+        // The store is still usable.
+        store.add_ticket(ticket_draft());
+        assert_eq!(store.snapshot().len(), 2);
+    }
+}
@@ -0,0 +1,273 @@
+use crate::backing::Backing;
+use crate::id::{IdGenerator, Sequential};
+use crate::iter::{Iter, IterMut};
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+use std::ops::{Index, IndexMut};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// Builds a stored value out of a draft, once its key has been assigned.
+pub trait FromDraft<K> {
+    type Draft;
+
+    fn from_draft(id: K, draft: Self::Draft) -> Self;
+}
+
+impl<K, T: FromDraft<K>> FromDraft<K> for Arc<Mutex<T>> {
+    type Draft = T::Draft;
+
+    fn from_draft(id: K, draft: Self::Draft) -> Self {
+        Arc::new(Mutex::new(T::from_draft(id, draft)))
+    }
+}
+
+impl<K, T: FromDraft<K>> FromDraft<K> for Arc<RwLock<T>> {
+    type Draft = T::Draft;
+
+    fn from_draft(id: K, draft: Self::Draft) -> Self {
+        Arc::new(RwLock::new(T::from_draft(id, draft)))
+    }
+}
+
+/// A keyed collection that assigns a fresh key to every value it stores.
+///
+/// `B` is the container holding the values, `G` produces the keys.
+#[derive(Clone, Debug)]
+pub struct Store<K, V, B = BTreeMap<K, V>, G = Sequential> {
+    pub(crate) items: B,
+    ids: G,
+    _entry: PhantomData<fn() -> (K, V)>,
+}
+
+impl<K, V, B, G> Store<K, V, B, G>
+where
+    K: Clone,
+    B: Backing<K, V>,
+    G: IdGenerator<K>,
+{
+    pub fn new() -> Self
+    where
+        G: Default,
+    {
+        Self::with_id_generator(G::default())
+    }
+
+    pub fn with_id_generator(ids: G) -> Self {
+        Self {
+            items: B::default(),
+            ids,
+            _entry: PhantomData,
+        }
+    }
+
+    /// Store the value built by `f` under a freshly generated key.
+    ///
+    /// # Panics
+    ///
+    /// If the id generator hands out a key that's already taken.
+    pub fn insert_with(&mut self, f: impl FnOnce(K) -> V) -> K {
+        let id = self.ids.next_id();
+        assert!(
+            self.items.get(&id).is_none(),
+            "The id generator repeated a key that's in use"
+        );
+        self.items.insert(id.clone(), f(id.clone()));
+        id
+    }
+
+    pub fn add_ticket(&mut self, draft: V::Draft) -> K
+    where
+        V: FromDraft<K>,
+    {
+        self.insert_with(|id| V::from_draft(id, draft))
+    }
+
+    pub fn get(&self, id: K) -> Option<&V> {
+        self.items.get(&id)
+    }
+
+    pub fn get_mut(&mut self, id: K) -> Option<&mut V> {
+        self.items.get_mut(&id)
+    }
+
+    pub fn remove(&mut self, id: K) -> Option<V> {
+        self.items.remove(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V, B> {
+        Iter::new(self.items.iter())
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V, B> {
+        IterMut::new(self.items.iter_mut())
+    }
+
+    /// Keep only the values for which `f` returns `true`.
+    ///
+    /// `f` gets mutable access, so values can be updated while they are being filtered.
+    pub fn retain(&mut self, mut f: impl FnMut(&mut V) -> bool) {
+        self.items.retain(|_, value| f(value))
+    }
+
+    /// Remove and return the values for which `f` returns `true`.
+    pub fn drain_filter(&mut self, mut f: impl FnMut(&mut V) -> bool) -> Vec<V> {
+        let doomed: Vec<K> = self
+            .items
+            .iter_mut()
+            .filter_map(|(id, value)| f(value).then(|| id.clone()))
+            .collect();
+        doomed
+            .into_iter()
+            .filter_map(|id| self.items.remove(&id))
+            .collect()
+    }
+}
+
+impl<K, V, B, G> Default for Store<K, V, B, G>
+where
+    K: Clone,
+    B: Backing<K, V>,
+    G: IdGenerator<K> + Default,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, B, G> Index<K> for Store<K, V, B, G>
+where
+    K: Clone,
+    B: Backing<K, V>,
+    G: IdGenerator<K>,
+{
+    type Output = V;
+
+    fn index(&self, index: K) -> &Self::Output {
+        self.get(index).unwrap()
+    }
+}
+
+impl<K, V, B, G> Index<&K> for Store<K, V, B, G>
+where
+    K: Clone,
+    B: Backing<K, V>,
+    G: IdGenerator<K>,
+{
+    type Output = V;
+
+    fn index(&self, index: &K) -> &Self::Output {
+        &self[index.clone()]
+    }
+}
+
+impl<K, V, B, G> IndexMut<K> for Store<K, V, B, G>
+where
+    K: Clone,
+    B: Backing<K, V>,
+    G: IdGenerator<K>,
+{
+    fn index_mut(&mut self, index: K) -> &mut Self::Output {
+        self.get_mut(index).unwrap()
+    }
+}
+
+impl<K, V, B, G> IndexMut<&K> for Store<K, V, B, G>
+where
+    K: Clone,
+    B: Backing<K, V>,
+    G: IdGenerator<K>,
+{
+    fn index_mut(&mut self, index: &K) -> &mut Self::Output {
+        &mut self[index.clone()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{Status, TicketId};
+    use crate::test_helpers::ticket_draft;
+    use crate::{HashTicketStore, LockedTicketStore, RwLockedTicketStore, Store, TicketStore};
+    use std::collections::HashMap;
+
+    #[test]
+    fn works() {
+        let mut store = TicketStore::new();
+
+        for _ in 0..5 {
+            let draft = ticket_draft();
+            let id = store.add_ticket(draft.clone());
+            let ticket = &store[id];
+            assert_eq!(draft.title, ticket.title);
+            assert_eq!(draft.description, ticket.description);
+            assert_eq!(ticket.status, Status::ToDo);
+
+            let ticket = &mut store[&id];
+            ticket.status = Status::InProgress;
+
+            let ticket = &store[id];
+            assert_eq!(ticket.status, Status::InProgress);
+        }
+
+        let ids: Vec<TicketId> = (&store).into_iter().map(|t| t.id).collect();
+        let sorted_ids = {
+            let mut v = ids.clone();
+            v.sort();
+            v
+        };
+        assert_eq!(ids, sorted_ids);
+        assert_eq!(store.len(), 5);
+    }
+
+    #[test]
+    fn hash_backing() {
+        let mut store = HashTicketStore::new();
+        let id = store.add_ticket(ticket_draft());
+        assert_eq!(store.get(id).unwrap().id, id);
+        assert_eq!(store.remove(id).unwrap().id, id);
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn shared_tickets() {
+        let mut store = LockedTicketStore::new();
+        let id = store.add_ticket(ticket_draft());
+        store[id].lock().unwrap().status = Status::Done;
+        assert_eq!(store.get(id).unwrap().lock().unwrap().status, Status::Done);
+
+        let mut store = RwLockedTicketStore::new();
+        let id = store.add_ticket(ticket_draft());
+        store[id].write().unwrap().status = Status::Done;
+        assert_eq!(store[&id].read().unwrap().status, Status::Done);
+    }
+
+    #[test]
+    fn custom_id_generator() {
+        let mut next = 100;
+        let mut store: Store<TicketId, u32, HashMap<_, _>, _> = Store::with_id_generator(|| {
+            next += 10;
+            TicketId::from(next)
+        });
+        let first = store.insert_with(|_| 1);
+        let second = store.insert_with(|_| 2);
+        assert_eq!(u64::from(first), 110);
+        assert_eq!(u64::from(second), 120);
+        assert_eq!(store[second], 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "repeated a key")]
+    fn a_repeated_id_is_not_overwritten() {
+        let mut store: Store<TicketId, u32, HashMap<_, _>, _> =
+            Store::with_id_generator(|| TicketId::from(7));
+        store.insert_with(|_| 1);
+        store.insert_with(|_| 2);
+    }
+}
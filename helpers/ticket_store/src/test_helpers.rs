@@ -0,0 +1,11 @@
+use crate::data::TicketDraft;
+use ticket_fields::test_helpers::{ticket_description, ticket_title};
+
+/// A function to generate a valid ticket draft,
+/// for test purposes.
+pub fn ticket_draft() -> TicketDraft {
+    TicketDraft {
+        title: ticket_title(),
+        description: ticket_description(),
+    }
+}
@@ -0,0 +1,288 @@
+use crate::backing::Backing;
+use crate::data::{Ticket, TicketId};
+use crate::id::IdGenerator;
+use crate::poison::{recover, TicketLockError, Violation};
+use crate::store::Store;
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TransactionError {
+    #[error("There is no ticket with id {0:?}")]
+    NotFound(TicketId),
+    #[error("Ticket {id:?} is quarantined: {violation}")]
+    Quarantined { id: TicketId, violation: Violation },
+    #[error("Gave up after {attempts} attempts, all of them conflicting with other writes")]
+    Conflict { attempts: usize },
+    #[error("The transaction was aborted: {0}")]
+    Aborted(String),
+}
+
+impl From<TicketLockError> for TransactionError {
+    fn from(e: TicketLockError) -> Self {
+        match e {
+            TicketLockError::NotFound(id) => TransactionError::NotFound(id),
+            TicketLockError::Quarantined { id, violation } => {
+                TransactionError::Quarantined { id, violation }
+            }
+        }
+    }
+}
+
+/// The tickets read and written by a transaction, see [`Store::transaction`].
+///
+/// Tickets are copied in on first access: changes made through
+/// [`get_mut`](Self::get_mut) stay private until the transaction commits.
+pub struct Transaction<'a> {
+    lookup: &'a dyn Fn(TicketId) -> Option<&'a RwLock<Ticket>>,
+    // Sorted by id: that's the order they are locked in on commit.
+    entries: BTreeMap<TicketId, Entry<'a>>,
+}
+
+struct Entry<'a> {
+    lock: &'a RwLock<Ticket>,
+    /// The ticket as it was read: if it changed by commit time, someone else wrote it.
+    seen: Ticket,
+    working: Ticket,
+}
+
+impl<'a> Transaction<'a> {
+    /// How many times a transaction is run before giving up on conflicts.
+    pub const MAX_ATTEMPTS: usize = 16;
+
+    pub fn get(&mut self, id: TicketId) -> Result<&Ticket, TransactionError> {
+        Ok(&self.entry(id)?.working)
+    }
+
+    pub fn get_mut(&mut self, id: TicketId) -> Result<&mut Ticket, TransactionError> {
+        Ok(&mut self.entry(id)?.working)
+    }
+
+    fn entry(&mut self, id: TicketId) -> Result<&mut Entry<'a>, TransactionError> {
+        if !self.entries.contains_key(&id) {
+            let lock = (self.lookup)(id).ok_or(TransactionError::NotFound(id))?;
+            let seen = recover(id, lock.read(), || lock.clear_poison())?.clone();
+            let entry = Entry {
+                lock,
+                working: seen.clone(),
+                seen,
+            };
+            self.entries.insert(id, entry);
+        }
+        Ok(self.entries.get_mut(&id).unwrap())
+    }
+
+    /// Apply every write, unless one of the tickets changed since it was read.
+    /// Returns whether it did.
+    fn commit(self) -> Result<bool, TicketLockError> {
+        let mut guards = self
+            .entries
+            .iter()
+            .map(|(id, entry)| recover(*id, entry.lock.write(), || entry.lock.clear_poison()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let conflict = guards
+            .iter()
+            .zip(self.entries.values())
+            .any(|(ticket, entry)| **ticket != entry.seen);
+        if conflict {
+            return Ok(false);
+        }
+        for (ticket, entry) in guards.iter_mut().zip(self.entries.into_values()) {
+            if entry.working != entry.seen {
+                **ticket = entry.working;
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl<B, G> Store<TicketId, Arc<RwLock<Ticket>>, B, G>
+where
+    B: Backing<TicketId, Arc<RwLock<Ticket>>>,
+    G: IdGenerator<TicketId>,
+{
+    /// Run `f` against the tickets, then apply all of its writes at once, or none.
+    ///
+    /// `f` works on private copies and holds no lock while it runs. On commit,
+    /// every ticket it touched is write-locked, in `TicketId` order: if any
+    /// of them changed in the meantime, the writes are thrown away and `f` is
+    /// run again, up to [`Transaction::MAX_ATTEMPTS`] times.
+    /// If `f` fails, nothing is written.
+    ///
+    /// Tickets whose lock was poisoned are recovered as described in
+    /// [`crate::poison`]: a quarantined ticket fails the transaction.
+    pub fn transaction<R>(
+        &self,
+        mut f: impl FnMut(&mut Transaction<'_>) -> Result<R, TransactionError>,
+    ) -> Result<R, TransactionError> {
+        let lookup = |id: TicketId| self.get(id).map(|ticket| ticket.as_ref());
+        for _ in 0..Transaction::MAX_ATTEMPTS {
+            let mut tx = Transaction {
+                lookup: &lookup,
+                entries: BTreeMap::new(),
+            };
+            let value = f(&mut tx)?;
+            if tx.commit()? {
+                return Ok(value);
+            }
+            std::thread::yield_now();
+        }
+        Err(TransactionError::Conflict {
+            attempts: Transaction::MAX_ATTEMPTS,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::Status;
+    use crate::test_helpers::ticket_draft;
+    use crate::RwLockedTicketStore;
+    use std::thread;
+
+    fn store() -> (RwLockedTicketStore, TicketId, TicketId) {
+        let mut store = RwLockedTicketStore::new();
+        let mut add = || store.add_ticket(ticket_draft());
+        let (a, b) = (add(), add());
+        (store, a, b)
+    }
+
+    #[test]
+    fn applies_every_write() {
+        let (store, a, b) = store();
+        store
+            .transaction(|tx| {
+                tx.get_mut(a)?.set_status(Status::Done);
+                tx.get_mut(b)?.set_status(Status::InProgress);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(store[a].read().unwrap().status, Status::Done);
+        assert_eq!(store[b].read().unwrap().status, Status::InProgress);
+    }
+
+    #[test]
+    fn applies_nothing_on_failure() {
+        let (store, a, b) = store();
+        let missing = TicketId::from(42);
+        let result = store.transaction(|tx| {
+            tx.get_mut(a)?.set_status(Status::Done);
+            tx.get_mut(missing)?.set_status(Status::Done);
+            Ok(())
+        });
+        assert_eq!(result, Err(TransactionError::NotFound(missing)));
+
+        let result: Result<(), _> = store.transaction(|tx| {
+            tx.get_mut(b)?.set_status(Status::Done);
+            Err(TransactionError::Aborted("Changed my mind".into()))
+        });
+        assert!(matches!(result, Err(TransactionError::Aborted(_))));
+
+        assert_eq!(store[a].read().unwrap().status, Status::ToDo);
+        assert_eq!(store[b].read().unwrap().status, Status::ToDo);
+    }
+
+    #[test]
+    fn fails_on_a_quarantined_ticket() {
+        let (store, a, b) = store();
+        let _ = std::panic::catch_unwind(|| {
+            let mut ticket = store[b].write().unwrap();
+            ticket.id = a;
+            panic!("Oops");
+        });
+        let result = store.transaction(|tx| {
+            tx.get_mut(a)?.set_status(Status::Done);
+            tx.get_mut(b)?.set_status(Status::ToDo);
+            Ok(())
+        });
+        assert_eq!(
+            result,
+            Err(TransactionError::Quarantined {
+                id: b,
+                violation: Violation::WrongId {
+                    expected: b,
+                    found: a,
+                }
+            })
+        );
+        assert_eq!(store[a].read().unwrap().status, Status::ToDo);
+    }
+
+    #[test]
+    fn retries_on_conflict() {
+        let (store, a, b) = store();
+        let mut attempts = 0;
+        store
+            .transaction(|tx| {
+                attempts += 1;
+                let status = tx.get(a)?.status;
+                if attempts == 1 {
+                    // Someone else gets in between the read and the commit.
+                    store[a].write().unwrap().set_status(Status::InProgress);
+                }
+                tx.get_mut(b)?.set_status(status);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(attempts, 2);
+        assert_eq!(store[b].read().unwrap().status, Status::InProgress);
+    }
+
+    #[test]
+    fn gives_up_eventually() {
+        let (store, a, _) = store();
+        let result = store.transaction(|tx| {
+            let status = tx.get(a)?.status;
+            let other = if status == Status::Done {
+                Status::ToDo
+            } else {
+                Status::Done
+            };
+            store[a].write().unwrap().set_status(other);
+            Ok(())
+        });
+        assert_eq!(
+            result,
+            Err(TransactionError::Conflict {
+                attempts: Transaction::MAX_ATTEMPTS
+            })
+        );
+    }
+
+    #[test]
+    fn concurrent_transactions_keep_tickets_in_step() {
+        let (store, a, b) = store();
+        thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..100 {
+                        // Conflicts are expected here: retry until it goes through.
+                        while store
+                            .transaction(|tx| {
+                                let next = match tx.get(a)?.status {
+                                    Status::ToDo => Status::InProgress,
+                                    _ => Status::ToDo,
+                                };
+                                tx.get_mut(a)?.set_status(next);
+                                tx.get_mut(b)?.set_status(next);
+                                Ok(())
+                            })
+                            .is_err()
+                        {}
+                    }
+                });
+            }
+            s.spawn(|| {
+                for _ in 0..100 {
+                    let (x, y) = store
+                        .transaction(|tx| Ok((tx.get(a)?.status, tx.get(b)?.status)))
+                        .unwrap_or((Status::Done, Status::Done));
+                    assert_eq!(x, y);
+                }
+            });
+        });
+        assert_eq!(store[a].read().unwrap().status, Status::ToDo);
+        assert_eq!(store[b].read().unwrap().status, Status::ToDo);
+    }
+}